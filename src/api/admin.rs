@@ -0,0 +1,238 @@
+use crate::api::utils::ResponseWrapper;
+use crate::model::evm;
+use crate::pkg::cache::cache::CacheKey;
+use crate::pkg::config::config::Config;
+use crate::pkg::config::store::{ConfigStore, ReloadReport};
+use crate::pkg::proxy::proxy::ProxyDetector;
+use actix_web::http::header;
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+pub struct CacheQuery {
+    chain: Option<String>,
+    address: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CacheSizes {
+    proxy: usize,
+    proxy_history: usize,
+    abi: usize,
+}
+
+#[derive(Serialize)]
+struct ProxyRangeItem {
+    from_block: u64,
+    to_block: u64,
+    proxy_type: Option<String>,
+    target: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CacheEntries {
+    key: String,
+    proxy_type: Option<String>,
+    proxy_target: Option<String>,
+    proxy_history: Vec<ProxyRangeItem>,
+    abi_functions: Option<usize>,
+    abi_events: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct CacheData {
+    sizes: CacheSizes,
+    entries: Option<CacheEntries>,
+}
+
+impl CacheQuery {
+    // the key of a single contract, None to address every cache entry
+    fn key(&self, config: &Config) -> Result<Option<CacheKey>, String> {
+        match (&self.chain, &self.address) {
+            (Some(chain), Some(address)) => {
+                let chain_config = config.find_chain(chain).map_err(|e| e.to_string())?;
+                let key = CacheKey::parse(chain_config.id, address).map_err(|e| e.to_string())?;
+                Ok(Some(key))
+            }
+            (None, None) => Ok(None),
+            _ => Err("chain and address must be given together".to_string()),
+        }
+    }
+}
+
+// the /admin routes only answer `Authorization: Bearer <admin.token>`,
+// they are disabled while no token is configured
+fn unauthorized(req: &HttpRequest, token: &str) -> Option<HttpResponse> {
+    let error = |message: &str| ResponseWrapper::<()> {
+        status: 0,
+        error_message: Some(message.to_string()),
+        data: None,
+    };
+    if token.is_empty() {
+        return Some(HttpResponse::Forbidden().json(error("admin routes are disabled, set admin.token")));
+    }
+
+    let bearer = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match bearer {
+        Some(bearer) if constant_time_eq(bearer.as_bytes(), token.as_bytes()) => None,
+        _ => Some(
+            HttpResponse::Unauthorized()
+                .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+                .json(error("missing or invalid admin token")),
+        ),
+    }
+}
+
+// every byte is compared, the response time does not tell how much of the token matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn cache_sizes() -> CacheSizes {
+    let (proxy, proxy_history) = ProxyDetector::cache_len();
+    CacheSizes {
+        proxy,
+        proxy_history,
+        abi: evm::function_event_map_cache_len(),
+    }
+}
+
+fn cache_entries(key: &CacheKey) -> CacheEntries {
+    let proxy = ProxyDetector::cached(key);
+    let abi = evm::cached_function_event_map(key);
+    CacheEntries {
+        key: key.to_string(),
+        proxy_type: proxy.as_ref().and_then(|p| p.proxy_type).map(|t| t.to_string()),
+        proxy_target: proxy.as_ref().and_then(|p| p.target).map(|addr| format!("{:#x}", addr)),
+        proxy_history: ProxyDetector::cached_history(key)
+            .iter()
+            .map(|entry| ProxyRangeItem {
+                from_block: entry.from_block,
+                to_block: entry.to_block,
+                proxy_type: entry.result.proxy_type.map(|t| t.to_string()),
+                target: entry.result.target.map(|addr| format!("{:#x}", addr)),
+            })
+            .collect(),
+        abi_functions: abi.as_ref().map(|(functions, _)| functions.len()),
+        abi_events: abi.as_ref().map(|(_, events)| events.len()),
+    }
+}
+
+// inspect the cache sizes, or the cached entries of one contract
+#[get("/admin/cache")]
+async fn cache_handler(req: HttpRequest, config: web::Data<ConfigStore>, query: web::Query<CacheQuery>) -> impl Responder {
+    let config = config.current();
+    if let Some(response) = unauthorized(&req, &config.admin.token) {
+        return response;
+    }
+    let mut response: ResponseWrapper<CacheData> = ResponseWrapper {
+        status: 0,
+        error_message: None,
+        data: None,
+    };
+
+    match query.key(&config) {
+        Ok(key) => {
+            response.status = 1;
+            response.data = Some(CacheData {
+                sizes: cache_sizes(),
+                entries: key.as_ref().map(cache_entries),
+            });
+            HttpResponse::Ok().json(response)
+        }
+        Err(e) => {
+            response.error_message = Some(e);
+            HttpResponse::BadRequest().json(response)
+        }
+    }
+}
+
+// invalidate the cached entries of one contract, or clear every cache
+#[delete("/admin/cache")]
+async fn cache_invalidate_handler(req: HttpRequest, config: web::Data<ConfigStore>, query: web::Query<CacheQuery>) -> impl Responder {
+    let config = config.current();
+    if let Some(response) = unauthorized(&req, &config.admin.token) {
+        return response;
+    }
+    let mut response: ResponseWrapper<CacheSizes> = ResponseWrapper {
+        status: 0,
+        error_message: None,
+        data: None,
+    };
+
+    match query.key(&config) {
+        Ok(Some(key)) => {
+            ProxyDetector::invalidate(&key);
+            evm::invalidate_function_event_map(&key);
+        }
+        Ok(None) => {
+            ProxyDetector::clear_cache();
+            evm::clear_function_event_map_cache();
+        }
+        Err(e) => {
+            response.error_message = Some(e);
+            return HttpResponse::BadRequest().json(response);
+        }
+    }
+
+    response.status = 1;
+    response.data = Some(cache_sizes());
+    HttpResponse::Ok().json(response)
+}
+
+// reload the config file, the running config is kept when the new one is invalid
+#[post("/admin/config/reload")]
+async fn config_reload_handler(req: HttpRequest, config: web::Data<ConfigStore>) -> impl Responder {
+    if let Some(response) = unauthorized(&req, &config.current().admin.token) {
+        return response;
+    }
+    let mut response: ResponseWrapper<ReloadReport> = ResponseWrapper {
+        status: 0,
+        error_message: None,
+        data: None,
+    };
+
+    match config.reload_and_log("admin request") {
+        Ok(report) => {
+            response.status = 1;
+            response.data = Some(report);
+            HttpResponse::Ok().json(response)
+        }
+        Err(e) => {
+            response.error_message = Some(e);
+            HttpResponse::BadRequest().json(response)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn test_unauthorized() {
+        let request = |authorization: Option<&str>| {
+            let mut req = TestRequest::delete().uri("/admin/cache");
+            if let Some(authorization) = authorization {
+                req = req.insert_header((header::AUTHORIZATION, authorization));
+            }
+            req.to_http_request()
+        };
+
+        assert!(unauthorized(&request(Some("Bearer admin-secret")), "admin-secret").is_none());
+        for authorization in [None, Some("Bearer admin"), Some("Bearer admin-secret2"), Some("admin-secret")] {
+            let response = unauthorized(&request(authorization), "admin-secret").expect("request let through");
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{:?}", authorization);
+        }
+
+        // no token configured, nobody gets in
+        let response = unauthorized(&request(Some("Bearer ")), "").expect("request let through");
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+}
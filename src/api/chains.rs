@@ -0,0 +1,52 @@
+use crate::api::utils::ResponseWrapper;
+use crate::pkg::config::config::ChainConfig;
+use crate::pkg::config::store::ConfigStore;
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct ChainItem {
+    id: u64,
+    name: String,
+    alias: String,
+    aliases: Vec<String>,
+    block_explorer: String,
+    capabilities: ChainCapabilities,
+}
+
+#[derive(Serialize)]
+struct ChainCapabilities {
+    explorer: bool, // transactions and ABIs can be fetched from the explorer API
+    trace: bool,
+    archive: bool,
+    rpc_endpoints: usize,
+}
+
+impl ChainItem {
+    fn new(chain: &ChainConfig) -> Self {
+        ChainItem {
+            id: chain.id,
+            name: chain.name.clone(),
+            alias: chain.alias.clone(),
+            aliases: chain.aliases.clone(),
+            block_explorer: chain.block_explorer.clone(),
+            capabilities: ChainCapabilities {
+                explorer: chain.has_explorer_api(),
+                trace: chain.trace,
+                archive: chain.archive,
+                rpc_endpoints: chain.all_rpc_endpoints().len(),
+            },
+        }
+    }
+}
+
+// the configured chains, any of id, name, alias and aliases can be passed as `chain`
+#[get("/chains")]
+async fn chains_handler(config: web::Data<ConfigStore>) -> impl Responder {
+    let response: ResponseWrapper<Vec<ChainItem>> = ResponseWrapper {
+        status: 1,
+        error_message: None,
+        data: Some(config.current().chains.iter().map(ChainItem::new).collect()),
+    };
+    HttpResponse::Ok().json(response)
+}
@@ -0,0 +1,112 @@
+use crate::api::utils::ResponseWrapper;
+use crate::pkg::config::config::ChainConfig;
+use crate::pkg::config::store::ConfigStore;
+use crate::sampler::coverage::{CoverageEntry, CoverageReport};
+use crate::sampler::sampler;
+use crate::sampler::window::SampleWindow;
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+#[derive(Deserialize)]
+pub struct CoverageQuery {
+    chain: String,
+    address: String,
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+    from_time: Option<u64>, // unix timestamp in seconds
+    to_time: Option<u64>,   // unix timestamp in seconds
+}
+
+impl CoverageQuery {
+    fn window(&self) -> SampleWindow {
+        SampleWindow {
+            from_block: self.from_block,
+            to_block: self.to_block,
+            from_time: self.from_time,
+            to_time: self.to_time,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct CoverageItem {
+    selector: String,
+    signature: String,
+    observed: bool,
+    observed_count: u64,
+    first_seen_block: Option<u64>,
+    last_seen_block: Option<u64>,
+    sample_hash: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CoverageData {
+    chain: String,
+    address: String,
+    implementation: Option<String>,
+    from_block: u64,
+    to_block: u64,
+    functions: Vec<CoverageItem>,
+    events: Vec<CoverageItem>,
+}
+
+impl From<&CoverageEntry> for CoverageItem {
+    fn from(entry: &CoverageEntry) -> Self {
+        CoverageItem {
+            selector: entry.selector.clone(),
+            signature: entry.signature.clone().unwrap_or("".to_string()),
+            observed: entry.observed(),
+            observed_count: entry.observed_count,
+            first_seen_block: entry.first_seen_block,
+            last_seen_block: entry.last_seen_block,
+            sample_hash: entry.sample_hash.clone(),
+        }
+    }
+}
+
+impl CoverageData {
+    fn new(chain: &str, report: &CoverageReport) -> Self {
+        CoverageData {
+            chain: chain.to_string(),
+            address: report.address.clone(),
+            implementation: report.implementation.clone(),
+            from_block: report.from_block,
+            to_block: report.to_block,
+            functions: report.functions.iter().map(CoverageItem::from).collect(),
+            events: report.events.iter().map(CoverageItem::from).collect(),
+        }
+    }
+}
+
+#[get("/coverage")]
+async fn coverage_handler(config: web::Data<ConfigStore>, query: web::Query<CoverageQuery>) -> impl Responder {
+    let mut response: ResponseWrapper<CoverageData> = ResponseWrapper {
+        status: 0,
+        error_message: None,
+        data: None,
+    };
+
+    let chain_config: ChainConfig = match config.current().find_chain(&query.chain) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            warn!(chain = %query.chain, error = %e, "unknown chain");
+            response.error_message = Some(e.to_string());
+            return HttpResponse::BadRequest().json(response);
+        }
+    };
+
+    let chain = chain_config.name.clone();
+    match sampler::Sampler::new(chain_config).coverage_report(&query.address, &query.window()).await {
+        Ok(report) => {
+            response.status = 1;
+            response.data = Some(CoverageData::new(&chain, &report));
+            HttpResponse::Ok().json(response)
+        }
+        Err(e) => {
+            error!(chain = %query.chain, address = %query.address, error = %e, "coverage report failed");
+            response.error_message = Some("error: please try it again or check the logs".to_string());
+            HttpResponse::BadRequest().json(response)
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use crate::api::utils::ResponseWrapper;
+use crate::pkg::config::store::ConfigStore;
+use crate::pkg::health::health::{self, ReadinessReport};
+use actix_web::{get, web, HttpResponse, Responder};
+use tracing::warn;
+
+// liveness: the process answers, no backend is called
+#[get("/healthz")]
+async fn healthz_handler() -> impl Responder {
+    let response: ResponseWrapper<&str> = ResponseWrapper {
+        status: 1,
+        error_message: None,
+        data: Some("ok"),
+    };
+    HttpResponse::Ok().json(response)
+}
+
+// readiness: the rpc and explorer API of every chain answer, 503 with the per-chain
+// breakdown otherwise
+#[get("/readyz")]
+async fn readyz_handler(config: web::Data<ConfigStore>) -> impl Responder {
+    let report = health::readiness(config.current()).await;
+    let mut response: ResponseWrapper<ReadinessReport> = ResponseWrapper {
+        status: 1,
+        error_message: None,
+        data: None,
+    };
+
+    if report.ready {
+        response.data = Some(report);
+        return HttpResponse::Ok().json(response);
+    }
+
+    let not_ready: Vec<&str> = report
+        .chains
+        .iter()
+        .filter(|chain| !chain.ready)
+        .map(|chain| chain.name.as_str())
+        .collect();
+    warn!(chains = %not_ready.join(", "), "not ready");
+    response.status = 0;
+    response.error_message = Some(format!("chains not ready: {}", not_ready.join(", ")));
+    response.data = Some(report);
+    HttpResponse::ServiceUnavailable().json(response)
+}
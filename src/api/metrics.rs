@@ -0,0 +1,10 @@
+use crate::pkg::telemetry::metrics;
+use actix_web::{get, HttpResponse, Responder};
+
+// Prometheus scrape endpoint, in the text exposition format instead of the json wrapper
+#[get("/metrics")]
+async fn metrics_handler() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type(prometheus::TEXT_FORMAT)
+        .body(metrics::render())
+}
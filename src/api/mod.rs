@@ -1,3 +1,9 @@
+pub mod admin;
+pub mod chains;
+pub mod coverage;
+pub mod health;
+pub mod metrics;
+pub mod proxy;
 pub mod sample;
 mod utils;
 
@@ -5,4 +11,13 @@ use actix_web::web;
 
 pub fn init_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(sample::sample_handler);
-}
\ No newline at end of file
+    cfg.service(coverage::coverage_handler);
+    cfg.service(proxy::proxy_handler);
+    cfg.service(admin::cache_handler);
+    cfg.service(admin::cache_invalidate_handler);
+    cfg.service(admin::config_reload_handler);
+    cfg.service(chains::chains_handler);
+    cfg.service(metrics::metrics_handler);
+    cfg.service(health::healthz_handler);
+    cfg.service(health::readyz_handler);
+}
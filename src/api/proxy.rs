@@ -0,0 +1,127 @@
+use crate::api::utils::ResponseWrapper;
+use crate::pkg::config::config::ChainConfig;
+use crate::pkg::config::store::ConfigStore;
+use crate::pkg::proxy::proxy::{DetectOutcome, DetectResult, ProxyDetector, StrategyOutcome};
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+#[derive(Deserialize)]
+pub struct ProxyQuery {
+    chain: String,
+    address: String,
+    block: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct StrategyItem {
+    strategy: String,
+    outcome: String, // matched, no_match, error
+    proxy_type: Option<String>,
+    target: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ProxyData {
+    chain: String,
+    address: String,
+    outcome: String, // proxy, not_proxy, inconclusive
+    proxy_type: Option<String>,
+    target: Option<String>,
+    conflict: bool,
+    admin: Option<String>,
+    beacon: Option<String>,
+    facets: Option<Vec<(String, String)>>, // (selector, facet)
+    diagnostics: Vec<StrategyItem>,
+}
+
+impl ProxyData {
+    fn new(chain: &str, address: &str, result: &DetectResult) -> Self {
+        ProxyData {
+            chain: chain.to_string(),
+            address: address.to_string(),
+            outcome: match result.outcome {
+                DetectOutcome::Proxy => "proxy",
+                DetectOutcome::NotProxy => "not_proxy",
+                DetectOutcome::Inconclusive => "inconclusive",
+            }
+            .to_string(),
+            proxy_type: result.proxy_type.map(|t| t.to_string()),
+            target: result.target.map(|addr| format!("{:#x}", addr)),
+            conflict: result.conflict,
+            admin: result.admin.map(|addr| format!("{:#x}", addr)),
+            beacon: result.beacon.map(|addr| format!("{:#x}", addr)),
+            facets: result.facets.as_ref().map(|facets| {
+                let mut items: Vec<(String, String)> = facets
+                    .iter()
+                    .map(|(selector, facet)| (selector.clone(), format!("{:#x}", facet)))
+                    .collect();
+                items.sort();
+                items
+            }),
+            diagnostics: result
+                .diagnostics
+                .iter()
+                .map(|d| {
+                    let (outcome, proxy_type, target, error) = match &d.outcome {
+                        StrategyOutcome::Matched(m) => (
+                            "matched",
+                            Some(m.proxy_type.to_string()),
+                            Some(format!("{:#x}", m.target)),
+                            None,
+                        ),
+                        StrategyOutcome::NoMatch => ("no_match", None, None, None),
+                        StrategyOutcome::Error(e) => ("error", None, None, Some(e.clone())),
+                    };
+                    StrategyItem {
+                        strategy: d.strategy.to_string(),
+                        outcome: outcome.to_string(),
+                        proxy_type,
+                        target,
+                        error,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+#[get("/proxy")]
+async fn proxy_handler(config: web::Data<ConfigStore>, query: web::Query<ProxyQuery>) -> impl Responder {
+    let mut response: ResponseWrapper<ProxyData> = ResponseWrapper {
+        status: 0,
+        error_message: None,
+        data: None,
+    };
+
+    let chain_config: ChainConfig = match config.current().find_chain(&query.chain) {
+        Ok(cfg) => cfg,
+        Err(e) => {
+            warn!(chain = %query.chain, error = %e, "unknown chain");
+            response.error_message = Some(e.to_string());
+            return HttpResponse::BadRequest().json(response);
+        }
+    };
+
+    let result = match ProxyDetector::new(&chain_config).await {
+        Ok(detector) => match query.block {
+            Some(block) => detector.detect_proxy_target_at(&query.address, block).await,
+            None => detector.detect_proxy_target(&query.address).await,
+        },
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(result) => {
+            response.status = 1;
+            response.data = Some(ProxyData::new(&chain_config.name, &query.address, &result));
+            HttpResponse::Ok().json(response)
+        }
+        Err(e) => {
+            error!(chain = %query.chain, address = %query.address, error = %e, "proxy detection failed");
+            response.error_message = Some("error: please try it again or check the logs".to_string());
+            HttpResponse::BadRequest().json(response)
+        }
+    }
+}
@@ -1,13 +1,31 @@
-use crate::{api::utils::ResponseWrapper, pkg::config::config::ChainConfig};
+use crate::api::utils::ResponseWrapper;
+use crate::pkg::config::config::ChainConfig;
+use crate::pkg::config::store::ConfigStore;
 use crate::sampler::sampler;
-use crate::CONFIG;
+use crate::sampler::window::SampleWindow;
 use actix_web::{get, web, HttpResponse, Responder};
 use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
 
 #[derive(Deserialize)]
 pub struct SampleQuery {
     chain: String,
     address: String,
+    from_block: Option<u64>,
+    to_block: Option<u64>,
+    from_time: Option<u64>, // unix timestamp in seconds
+    to_time: Option<u64>,   // unix timestamp in seconds
+}
+
+impl SampleQuery {
+    fn window(&self) -> SampleWindow {
+        SampleWindow {
+            from_block: self.from_block,
+            to_block: self.to_block,
+            from_time: self.from_time,
+            to_time: self.to_time,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -16,39 +34,51 @@ struct SampleItem {
     tx_hash: String,
     method_id: String,
     method_signature: String,
+    implementation: String,
     logs: Vec<(String, String)>, // (event id, event signature)
+    stats: SampleStats,
+}
+
+#[derive(Serialize)]
+struct SampleStats {
+    occurrences: u64,
+    share: f64,
+    distinct_senders: u64,
+    failure_rate: f64,
+    median_gas_used: u64,
 }
 
 #[get("/sample")]
-async fn sample_handler(query: web::Query<SampleQuery>) -> impl Responder {
+async fn sample_handler(config: web::Data<ConfigStore>, query: web::Query<SampleQuery>) -> impl Responder {
     let mut response: ResponseWrapper<Vec<SampleItem>> = ResponseWrapper {
         status: 0,
         error_message: None,
         data: None,
     };
 
-    let chain_config: ChainConfig;
-    match CONFIG.chain_by_name(&query.chain) {
-        Ok(cfg) => chain_config = cfg,
+    let chain_config: ChainConfig = match config.current().find_chain(&query.chain) {
+        Ok(cfg) => cfg,
         Err(e) => {
-            println!("{}", e);
-            response.error_message = Some("error: please try it again or check the logs".to_string());
+            warn!(chain = %query.chain, error = %e, "unknown chain");
+            response.error_message = Some(e.to_string());
             return HttpResponse::BadRequest().json(response);
         }
-    }
+    };
 
-    let transactions = sampler::Sampler::transaction_samples(&chain_config, &query.address).await;
-    match transactions {
-        Ok(txs) => {
-            let items: Vec<SampleItem> = txs
+    let chain = chain_config.name.clone();
+    let samples = sampler::Sampler::new(chain_config).transaction_samples(&query.address, &query.window()).await;
+    match samples {
+        Ok(samples) => {
+            let items: Vec<SampleItem> = samples
                 .iter()
-                .map(|tx| SampleItem {
-                    chain: query.chain.clone(),
+                .map(|sample| (&sample.transaction, &sample.stats))
+                .map(|(tx, stats)| SampleItem {
+                    chain: chain.clone(),
                     tx_hash: tx.hash.clone(),
                     method_id: tx.method_id.clone(),
                     method_signature: tx.method_signature.clone().unwrap_or("".to_string()),
+                    implementation: tx.implementation.clone().unwrap_or("".to_string()),
                     logs: if let Some(receipt) = &tx.receipt {
-                        println!("{:?}", receipt.logs);
                         receipt
                             .logs
                             .iter()
@@ -63,6 +93,13 @@ async fn sample_handler(query: web::Query<SampleQuery>) -> impl Responder {
                     } else {
                         Vec::new()
                     },
+                    stats: SampleStats {
+                        occurrences: stats.occurrences,
+                        share: stats.share,
+                        distinct_senders: stats.distinct_senders,
+                        failure_rate: stats.failure_rate,
+                        median_gas_used: stats.median_gas_used,
+                    },
                 })
                 .collect();
             response.status = 1;
@@ -70,7 +107,7 @@ async fn sample_handler(query: web::Query<SampleQuery>) -> impl Responder {
             HttpResponse::Ok().json(response)
         }
         Err(e) => {
-            println!("{}", e);
+            error!(chain = %query.chain, address = %query.address, error = %e, "sampling failed");
             response.error_message = Some("error: please try it again or check the logs".to_string());
             HttpResponse::BadRequest().json(response)
         }
@@ -0,0 +1,79 @@
+use evm_tx_sampler::pkg::config::config::{take_config_arg, Config, DEFAULT_CONFIG_PATH};
+use evm_tx_sampler::pkg::telemetry::logging;
+use evm_tx_sampler::sampler::coverage::CoverageEntry;
+use evm_tx_sampler::sampler::sampler::Sampler;
+use evm_tx_sampler::sampler::window::SampleWindow;
+use std::env;
+
+// the chain is given by name, alias or id
+const USAGE: &str =
+    "[--config <path>] <chain> <address> [--from-block N] [--to-block N] [--from-time TS] [--to-time TS]";
+
+// usage: cargo run --bin coverage -- [--config <path>] <chain> <address> [window options]
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let mut args: Vec<String> = env::args().collect();
+    let config_path = take_config_arg(&mut args)?.unwrap_or(DEFAULT_CONFIG_PATH.to_string());
+    if args.len() < 3 {
+        eprintln!("usage: {} {}", args[0], USAGE);
+        std::process::exit(2);
+    }
+
+    let window = parse_window(&args[3..])?;
+    let config = Config::load(&config_path)?;
+    logging::init(&config.log);
+    let chain = config.find_chain(&args[1])?;
+    let report = Sampler::new(chain).coverage_report(&args[2], &window).await?;
+
+    println!("contract:       {}", report.address);
+    if let Some(implementation) = &report.implementation {
+        println!("implementation: {}", implementation);
+    }
+    println!("window:         {} - {}", report.from_block, report.to_block);
+
+    println!("\nfunctions:");
+    for entry in &report.functions {
+        print_entry(entry);
+    }
+    println!("\nevents:");
+    for entry in &report.events {
+        print_entry(entry);
+    }
+
+    Ok(())
+}
+
+fn parse_window(options: &[String]) -> eyre::Result<SampleWindow> {
+    let mut window = SampleWindow::default();
+    for pair in options.chunks(2) {
+        let [flag, value] = pair else {
+            eyre::bail!("missing value for option {}", pair[0]);
+        };
+        let value: u64 = value.parse()?;
+        match flag.as_str() {
+            "--from-block" => window.from_block = Some(value),
+            "--to-block" => window.to_block = Some(value),
+            "--from-time" => window.from_time = Some(value),
+            "--to-time" => window.to_time = Some(value),
+            _ => eyre::bail!("unknown option {}", flag),
+        }
+    }
+    Ok(window)
+}
+
+fn print_entry(entry: &CoverageEntry) {
+    let signature = entry.signature.as_deref().unwrap_or("<not in abi>");
+    if !entry.observed() {
+        println!("  {:<10} {:<60} never observed", entry.selector, signature);
+        return;
+    }
+    println!(
+        "  {:<10} {:<60} count={} first={} last={} sample={}",
+        entry.selector,
+        signature,
+        entry.observed_count,
+        entry.first_seen_block.unwrap_or_default(),
+        entry.last_seen_block.unwrap_or_default(),
+        entry.sample_hash.as_deref().unwrap_or(""),
+    );
+}
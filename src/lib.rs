@@ -1,15 +1,23 @@
+// every module keeps its main file next to a mod.rs of the same name, e.g. pkg/config/config.rs
+#![allow(clippy::module_inception)]
+
 pub mod api;
 pub mod model;
 pub mod pkg;
 pub mod sampler;
 
+#[cfg(test)]
 use once_cell::sync::Lazy;
-use pkg::config::config::Config;
+#[cfg(test)]
+use pkg::config::config::{Config, DEFAULT_CONFIG_PATH};
+#[cfg(test)]
 use std::sync::Arc;
 
-pub static CONFIG: Lazy<Arc<Config>> = Lazy::new(|| {
-    let config_path = "config/production.toml";
-    let config = Config::load(config_path).expect("Failed to load config");
+// configuration of the tests that hit the live chains, the library itself takes
+// its Config from the caller
+#[cfg(test)]
+pub(crate) static CONFIG: Lazy<Arc<Config>> = Lazy::new(|| {
+    let config = Config::load(DEFAULT_CONFIG_PATH).expect("Failed to load config");
     Arc::new(config)
 });
 
@@ -21,7 +29,7 @@ mod tests {
     fn test_config() {
         assert_eq!(CONFIG.chains.len(), 1);
 
-        let chain = CONFIG.chains.get(0).unwrap();
+        let chain = CONFIG.chains.first().unwrap();
         assert_eq!(chain.id, 1);
         assert_eq!(chain.name, "ethereum");
         assert_eq!(chain.alias, "Ethereum Mainnet");
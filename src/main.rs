@@ -1,11 +1,94 @@
-use actix_web::{App, HttpServer};
+use actix_cors::Cors;
+use actix_web::dev::Service;
+use actix_web::middleware::Condition;
+use actix_web::{error, web, App, HttpServer};
 use evm_tx_sampler::api;
+use evm_tx_sampler::pkg::config::config::{take_config_arg, ServerConfig, DEFAULT_CONFIG_PATH};
+use evm_tx_sampler::pkg::config::store::{ConfigStore, WATCH_INTERVAL};
+use evm_tx_sampler::pkg::telemetry::{logging, metrics};
+use std::env;
+use std::time::{Duration, Instant};
+use tracing::info;
+use tracing_actix_web::TracingLogger;
 
-
+// usage: cargo run -- [--config <path>]
+// the chains are reloaded when the file changes, on SIGHUP and on POST /admin/config/reload,
+// the [server] section only at startup. a config from CONFIG_CONTENT only changes on restart.
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    HttpServer::new(|| App::new().configure(api::init_routes))
-        .bind(("127.0.0.1", 8502))?
-        .run()
-        .await
+    let mut args: Vec<String> = env::args().collect();
+    let config_path = take_config_arg(&mut args)
+        .map_err(std::io::Error::other)?
+        .unwrap_or(DEFAULT_CONFIG_PATH.to_string());
+    let store = ConfigStore::load(&config_path)
+        .map_err(|e| std::io::Error::other(format!("failed to load config {}: {}", config_path, e)))?;
+    let server = store.current().server.clone();
+    logging::init(&store.current().log);
+    let store = web::Data::new(store);
+
+    store.clone().into_inner().watch(WATCH_INTERVAL);
+    #[cfg(unix)]
+    store.clone().into_inner().reload_on_sighup().map_err(std::io::Error::other)?;
+
+    let app_server = server.clone();
+    let mut http_server = HttpServer::new(move || {
+        let request_timeout = Some(app_server.request_timeout)
+            .filter(|timeout| *timeout > 0)
+            .map(Duration::from_secs);
+        App::new()
+            .app_data(store.clone())
+            .app_data(web::PayloadConfig::new(app_server.max_body_size))
+            .app_data(web::JsonConfig::default().limit(app_server.max_json_size))
+            .wrap_fn(move |req, srv| {
+                let response = srv.call(req);
+                async move {
+                    match request_timeout {
+                        Some(timeout) => tokio::time::timeout(timeout, response)
+                            .await
+                            .unwrap_or_else(|_| Err(error::ErrorGatewayTimeout("request timed out"))),
+                        None => response.await,
+                    }
+                }
+            })
+            // counted outside the timeout, so timed out requests show up as 504
+            .wrap_fn(|req, srv| {
+                let route = req.match_pattern().unwrap_or_else(|| "unmatched".to_string());
+                let started = Instant::now();
+                let response = srv.call(req);
+                async move {
+                    let response = response.await;
+                    let status = match &response {
+                        Ok(response) => response.status(),
+                        Err(e) => e.as_response_error().status_code(),
+                    };
+                    metrics::observe_request(&route, status.as_u16(), started.elapsed());
+                    response
+                }
+            })
+            .wrap(Condition::new(!app_server.cors_origins.is_empty(), cors(&app_server)))
+            .wrap(TracingLogger::default())
+            .configure(api::init_routes)
+    })
+    .bind((server.host.as_str(), server.port))?;
+    if let Some(workers) = server.workers {
+        http_server = http_server.workers(workers);
+    }
+
+    info!(host = %server.host, port = server.port, config = %config_path, "listening");
+
+    http_server.run().await
+}
+
+fn cors(server: &ServerConfig) -> Cors {
+    let cors = Cors::default()
+        .allowed_methods(vec!["GET", "POST", "DELETE"])
+        .allow_any_header()
+        .max_age(3600);
+    if server.cors_origins.iter().any(|origin| origin == "*") {
+        return cors.allow_any_origin();
+    }
+    server
+        .cors_origins
+        .iter()
+        .fold(cors, |cors, origin| cors.allowed_origin(origin))
 }
@@ -1,14 +1,28 @@
 use std::str::FromStr;
 
+use crate::pkg::cache::cache::{CacheKey, TtlCache};
 use crate::pkg::config::{client::*, config::ChainConfig};
-use crate::pkg::proxy::proxy::ProxyDetector;
+use crate::pkg::proxy::proxy::{DetectOutcome, DetectResult, ProxyDetector};
+use alloy::eips::BlockId;
 use alloy::{primitives::*, providers::Provider};
-use eyre::{OptionExt, Result};
+use eyre::{eyre, OptionExt, Result};
 use hex::ToHexExt;
+use lazy_static::lazy_static;
 use map::hash_map::HashMap;
+use std::time::Duration;
+use tracing::{instrument, trace, warn};
 
-use cached::proc_macro::cached;
-use cached::SizedCache;
+// (function map, event map) of a contract ABI
+type AbiMaps = (HashMap<String, String>, HashMap<String, String>);
+
+const ABI_CACHE_SIZE: usize = 100;
+const ABI_CACHE_TTL: Duration = Duration::from_secs(24 * 3600);
+
+lazy_static! {
+    // verified ABIs rarely change, errors (e.g. unverified contracts) are not cached
+    static ref ABI_CACHE: TtlCache<CacheKey, std::result::Result<AbiMaps, String>> =
+        TtlCache::new("abi", ABI_CACHE_SIZE);
+}
 
 #[derive(Debug)]
 pub struct ContractInfo {
@@ -32,6 +46,7 @@ pub struct Transaction {
     pub max_priority_fee_per_gas: Option<u128>,
     pub max_fee_per_gas: Option<u128>,
     pub hash: String,
+    pub implementation: Option<String>,   // contract whose ABI decoded the transaction, if `to` is a proxy
     pub input: String,
     pub method_id: String,                // e.g. 0x88316456
     pub method_signature: Option<String>, // e.g. mint(tuple params)
@@ -46,6 +61,26 @@ pub struct Transaction {
 
 impl Transaction {
     pub async fn new(chain_config: &ChainConfig, tx_hash: &str) -> Result<Self> {
+        Self::decode(chain_config, tx_hash, None).await
+    }
+
+    // decode with the ABI of the given implementation instead of resolving the proxy of `to`,
+    // used when the implementation history of the proxy is already known
+    pub async fn with_implementation(
+        chain_config: &ChainConfig,
+        tx_hash: &str,
+        implementation: Address,
+    ) -> Result<Self> {
+        Self::decode(chain_config, tx_hash, Some(implementation)).await
+    }
+
+    // one span per hydrated sample transaction
+    #[instrument(name = "hydrate", skip(chain_config, implementation), fields(chain = %chain_config.name))]
+    async fn decode(
+        chain_config: &ChainConfig,
+        tx_hash: &str,
+        implementation: Option<Address>,
+    ) -> Result<Self> {
         let provider = new_rpc_client(chain_config).await?;
 
         let tx_hash_b256 = B256::from_str(tx_hash)?;
@@ -82,8 +117,8 @@ impl Transaction {
 
         if to == "0x" {
             return Ok(Self {
-                block_hash: block_hash,
-                block_number: block_number,
+                block_hash,
+                block_number,
                 chain: chain_config.clone(),
                 from_address: format!("0x{}", tx.from.encode_hex()),
                 gas: tx.gas,
@@ -92,8 +127,9 @@ impl Transaction {
                 max_fee_per_gas: tx.max_fee_per_gas,
                 // hash: format!("0x{}", String::from_str(tx_hash)?),
                 hash: String::from_str(tx_hash)?,
+                implementation: None,
                 input: tx.input.encode_hex(),
-                method_id: method_id,
+                method_id,
                 method_signature: None,
                 nonce: tx.nonce,
                 timestamp: block_timestamp,
@@ -105,23 +141,59 @@ impl Transaction {
             });
         }
 
-        let proxy_detector = ProxyDetector::new(chain_config).await?;
-        let proxy = proxy_detector.detect_proxy_target(to.as_ref()).await?;
+        let to_address = tx.to.ok_or_eyre("empty to")?;
+        let (impl_address, (mut function_map, event_map)) = match implementation {
+            Some(addr) => (addr, function_event_map(chain_config, &addr).await?),
+            None => {
+                // decode historic calldata with the implementation active at its block. that state
+                // needs an archive node, the current implementation is the fallback without one
+                // and when the detection at the block is inconclusive. most contracts are not
+                // proxies, the cached latest detection spares them the historical one
+                let proxy_detector = ProxyDetector::new(chain_config).await?;
+                let mut proxy = proxy_detector.detect_proxy_target(to.as_ref()).await?;
+                let mut state_block = BlockId::latest();
+                if chain_config.archive && proxy.outcome != DetectOutcome::NotProxy {
+                    let historical = proxy_detector
+                        .detect_proxy_target_at(to.as_ref(), block_number)
+                        .await?;
+                    if historical.is_conclusive() {
+                        proxy = historical;
+                        state_block = BlockId::number(block_number);
+                    }
+                }
+                let maps = resolved_function_event_map(chain_config, &to_address, &proxy).await?;
+                match &proxy.facets {
+                    Some(facets) => {
+                        let facet = match facets.get(&method_id) {
+                            Some(facet) => Some(*facet),
+                            None => proxy_detector
+                                .facet_address(to_address, &method_id, state_block)
+                                .await
+                                .ok()
+                                .flatten(),
+                        };
+                        (facet.unwrap_or(to_address), maps)
+                    }
+                    None => (proxy.target.unwrap_or(to_address), maps),
+                }
+            }
+        };
 
-        let mut impl_address = tx.to.ok_or_eyre("empty to")?;
-        if let Some(addr) = proxy.target {
-            impl_address = addr;
+        // a diamond selector missing from the merged map is decoded with its own facet's ABI
+        if !function_map.contains_key(&method_id) && impl_address != to_address {
+            if let Ok((functions, _)) = function_event_map(chain_config, &impl_address).await {
+                if let Some(signature) = functions.get(&method_id) {
+                    function_map.insert(method_id.clone(), signature.clone());
+                }
+            }
         }
-
-        let (function_map, event_map) =
-            function_event_map(chain_config, &impl_address).await?;
         let method_signature = function_map.get(&method_id).cloned();
 
         let receipt = Receipt::new(provider, tx_hash, &event_map).await?;
 
         Ok(Self {
-            block_hash: block_hash,
-            block_number: block_number,
+            block_hash,
+            block_number,
             chain: chain_config.clone(),
             from_address: format!("0x{}", tx.from.encode_hex()),
             gas: tx.gas,
@@ -130,16 +202,21 @@ impl Transaction {
             max_fee_per_gas: tx.max_fee_per_gas,
             // hash: format!("0x{}", String::from_str(tx_hash)?),
             hash: String::from_str(tx_hash)?,
+            implementation: if impl_address != to_address {
+                Some(format!("{:#x}", impl_address))
+            } else {
+                None
+            },
             input: tx.input.encode_hex(),
-            method_id: method_id,
-            method_signature: method_signature,
+            method_id,
+            method_signature,
             nonce: tx.nonce,
             timestamp: block_timestamp,
             to_address: to,
             transaction_index: tx.transaction_index.ok_or_eyre("invalid tx index")?,
             transaction_type: tx.transaction_type.ok_or_eyre("invalid tx type")?,
             value: tx.value.to_string(),
-            receipt: receipt,
+            receipt,
         })
     }
 
@@ -192,7 +269,7 @@ impl Receipt {
         let logs = receipt
             .inner
             .logs()
-            .into_iter()
+            .iter()
             .map(|log| {
                 let event_id = log
                     .topic0()
@@ -200,17 +277,17 @@ impl Receipt {
                     .unwrap_or_else(|| "0x0".to_string());
 
                 let event_signature = event_map.get(&event_id).cloned();
-                println!("{} {:?} {:?}", event_id, event_signature, event_map);
+                trace!(event_id = %event_id, event_signature = ?event_signature, "log decoded");
 
                 Log {
                     address: format!("{:#x}", log.address()),
                     data: hex::encode(log.data().data.clone()),
-                    event_id: event_id,
-                    event_signature: event_signature,
+                    event_id,
+                    event_signature,
                     log_index: log.log_index,
                     topics: log
                         .topics()
-                        .into_iter()
+                        .iter()
                         .map(|topic| format!("{:#x}", topic))
                         .collect(),
                 }
@@ -218,28 +295,102 @@ impl Receipt {
             .collect();
 
         Ok(Some(Self {
-            contract_address: contract_address,
+            contract_address,
             effective_gas_price: receipt.effective_gas_price,
             gas_used: receipt.gas_used,
             hash: format!("{:#x}", receipt.transaction_hash),
-            logs: logs,
+            logs,
             status: receipt.status(),
         }))
     }
 }
 
-#[cached(
-    ty = "SizedCache<String, (HashMap<String, String>, HashMap<String, String>)>",
-    create = "{ SizedCache::with_size(100) }",
-    convert = r#"{ format!("{}{}", chain_config.name, address.encode_hex()) }"#,
-    result = true,
-)]
+// ABI maps of the contract at `address`, decoded with the implementation of a proxy
+// or with the ABIs of all the facets of a diamond
+pub async fn resolved_function_event_map(
+    chain_config: &ChainConfig,
+    address: &Address,
+    proxy: &DetectResult,
+) -> Result<(HashMap<String, String>, HashMap<String, String>)> {
+    if let Some(facets) = &proxy.facets {
+        return diamond_function_event_map(chain_config, facets).await;
+    }
+    function_event_map(chain_config, &proxy.target.unwrap_or(*address)).await
+}
+
+// merge the ABIs of the facets of a diamond, each selector takes the signature from its own facet
+pub async fn diamond_function_event_map(
+    chain_config: &ChainConfig,
+    facets: &std::collections::HashMap<String, Address>,
+) -> Result<(HashMap<String, String>, HashMap<String, String>)> {
+    let mut facet_maps = HashMap::new();
+    for facet in facets.values() {
+        if facet_maps.contains_key(facet) {
+            continue;
+        }
+        match function_event_map(chain_config, facet).await {
+            Ok(maps) => {
+                facet_maps.insert(*facet, maps);
+            }
+            // unverified facets are left undecoded
+            Err(e) => warn!(facet = %format!("{:#x}", facet), error = %e, "facet abi not found"),
+        }
+    }
+
+    let mut function_map = HashMap::new();
+    let mut event_map = HashMap::new();
+    for (selector, facet) in facets {
+        if let Some(signature) = facet_maps.get(facet).and_then(|(functions, _)| functions.get(selector)) {
+            function_map.insert(selector.clone(), signature.clone());
+        }
+    }
+    for (_, events) in facet_maps.values() {
+        for (topic, signature) in events {
+            event_map.insert(topic.clone(), signature.clone());
+        }
+    }
+
+    Ok((function_map, event_map))
+}
+
 pub async fn function_event_map(
     chain_config: &ChainConfig,
     address: &Address,
+) -> Result<(HashMap<String, String>, HashMap<String, String>)> {
+    ABI_CACHE
+        .get_or_insert_with(
+            CacheKey::new(chain_config.id, *address),
+            ABI_CACHE_TTL,
+            |maps| maps.is_ok(),
+            || async { fetch_function_event_map(chain_config, address).await.map_err(|e| e.to_string()) },
+        )
+        .await
+        .map_err(|e| eyre!(e))
+}
+
+// cached ABI maps of the contract
+pub fn cached_function_event_map(key: &CacheKey) -> Option<AbiMaps> {
+    ABI_CACHE.get(key).and_then(|maps| maps.ok())
+}
+
+pub fn invalidate_function_event_map(key: &CacheKey) {
+    ABI_CACHE.remove(key);
+}
+
+pub fn clear_function_event_map_cache() {
+    ABI_CACHE.clear();
+}
+
+pub fn function_event_map_cache_len() -> usize {
+    ABI_CACHE.len()
+}
+
+async fn fetch_function_event_map(
+    chain_config: &ChainConfig,
+    address: &Address,
 ) -> Result<(HashMap<String, String>, HashMap<String, String>)> {
     let scan = new_scan_client(chain_config)?;
-    let abi = scan.contract_abi(address.clone()).await?;
+    let abi = traced_explorer_call(chain_config, "getabi", scan.contract_abi(*address)).await?;
 
     let mut function_map = HashMap::new();
     let mut event_map = HashMap::new();
@@ -247,14 +398,14 @@ pub async fn function_event_map(
     for item in abi.functions() {
         let signature = item.full_signature();
         let selector = item.selector();
-        let selector_hex = format!("0x{}", hex::encode(&selector));
+        let selector_hex = format!("0x{}", hex::encode(selector));
         function_map.insert(selector_hex, signature);
     }
 
     for item in abi.events() {
         let signature = item.full_signature();
         let selector = item.selector();
-        let selector_hex = format!("0x{}", hex::encode(&selector));
+        let selector_hex = format!("0x{}", hex::encode(selector));
         event_map.insert(selector_hex, signature);
     }
 
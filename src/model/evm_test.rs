@@ -7,15 +7,22 @@ mod tests {
 
     use alloy::primitives::Address;
 
+    use crate::pkg::config::client::new_rpc_client;
+    use crate::pkg::mock::server::MockServer;
     use crate::CONFIG;
 
     use super::*;
 
+    // tests/fixtures/erc20_token.json
+    const TOKEN: &str = "0xe200000000000000000000000000000000000001";
+    const TRANSFER_TX: &str = "0x0403930f5ca59bfcb249c5f674ac8a1d721e685af3b6088adff104ac2a317d44";
+    const TRANSFER_TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
     #[tokio::test]
     async fn test_transaction_new() {
-        let chain_config = CONFIG.chains.get(0).expect("chain config not found");
+        let chain_config = CONFIG.chains.first().expect("chain config not found");
         let tx_hash = "0xfaefcf34bca70cbdbe605864c6651974906f8e8b117c8b7b8c79cdaded52f56b";
-        let result = evm::Transaction::new(chain_config, &tx_hash).await;
+        let result = evm::Transaction::new(chain_config, tx_hash).await;
         match result {
             Ok(tx) => {
                 assert_eq!(tx.hash, tx_hash);
@@ -27,7 +34,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_function_event_map() {
-        let chain_config = CONFIG.chains.get(0).expect("chain config not found");
+        let chain_config = CONFIG.chains.first().expect("chain config not found");
         let address = match Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48") {
             Ok(addr) => addr,
             Err(e) => panic!("{}", e)
@@ -40,4 +47,54 @@ mod tests {
         assert!(!events.is_empty());
         println!("{:?} {:?}", functions, events);
     }
+
+    #[tokio::test]
+    async fn test_transaction_new_offline() {
+        let server = MockServer::start(1, "erc20_token").await.expect("mock server not started");
+        let tx = match evm::Transaction::new(&server.chain(), TRANSFER_TX).await {
+            Ok(tx) => tx,
+            Err(e) => panic!("{}", e),
+        };
+
+        assert_eq!(tx.hash, TRANSFER_TX);
+        assert_eq!(tx.block_number, 98);
+        assert_eq!(tx.to_address, TOKEN);
+        assert_eq!(tx.method_id, "0xa9059cbb");
+        assert!(tx.method_signature.expect("method not decoded").contains("transfer"));
+        assert_eq!(tx.implementation, None);
+
+        let receipt = tx.receipt.expect("receipt not found");
+        assert!(receipt.status);
+        assert_eq!(receipt.logs.len(), 1);
+        assert_eq!(receipt.logs[0].event_id, TRANSFER_TOPIC);
+        assert!(receipt.logs[0].event_signature.as_ref().expect("event not decoded").contains("Transfer"));
+    }
+
+    #[tokio::test]
+    async fn test_receipt_new_offline() {
+        let server = MockServer::start(1, "erc20_token").await.expect("mock server not started");
+        let provider = new_rpc_client(&server.chain()).await.expect("provider not created");
+        let mut event_map = alloy::primitives::map::HashMap::new();
+        event_map.insert(TRANSFER_TOPIC.to_string(), "Transfer(address,address,uint256)".to_string());
+
+        let receipt = match evm::Receipt::new(provider, TRANSFER_TX, &event_map).await {
+            Ok(Some(receipt)) => receipt,
+            Ok(None) => panic!("receipt not found"),
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(receipt.hash, TRANSFER_TX);
+        assert_eq!(receipt.gas_used, 35_000);
+        assert_eq!(receipt.contract_address, None);
+        assert_eq!(receipt.logs[0].address, TOKEN);
+        assert_eq!(receipt.logs[0].event_signature.as_deref(), Some("Transfer(address,address,uint256)"));
+        assert_eq!(receipt.logs[0].topics.len(), 3);
+
+        // a hash the node does not know
+        let missing = "0x0000000000000000000000000000000000000000000000000000000000000001";
+        let provider = new_rpc_client(&server.chain()).await.expect("provider not created");
+        match evm::Receipt::new(provider, missing, &event_map).await {
+            Ok(receipt) => assert!(receipt.is_none()),
+            Err(e) => panic!("{}", e),
+        }
+    }
 }
@@ -0,0 +1,271 @@
+use crate::pkg::telemetry::metrics;
+use alloy::primitives::Address;
+use cached::{Cached, SizedCache};
+use eyre::{eyre, Result};
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// key of every per-contract cache, the address is parsed so that its spelling
+// (checksum, lower or upper case) does not matter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CacheKey {
+    pub chain_id: u64,
+    pub address: Address,
+}
+
+impl CacheKey {
+    pub fn new(chain_id: u64, address: Address) -> Self {
+        CacheKey { chain_id, address }
+    }
+
+    pub fn parse(chain_id: u64, address: &str) -> Result<Self> {
+        let address = address
+            .parse::<Address>()
+            .map_err(|e| eyre!("Invalid address {}: {}", address, e))?;
+        Ok(CacheKey::new(chain_id, address))
+    }
+}
+
+impl std::fmt::Display for CacheKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{:#x}", self.chain_id, self.address)
+    }
+}
+
+struct Entry<V> {
+    value: V,
+    expires_at: Instant,
+}
+
+type Inflight<K> = Mutex<HashMap<K, Arc<tokio::sync::Mutex<()>>>>;
+
+// share of a lookup in the inflight lock of its key. the last one to drop removes the
+// lock, also when the lookup is cancelled mid-flight.
+struct InflightGuard<'a, K: Hash + Eq> {
+    inflight: &'a Inflight<K>,
+    key: K,
+    lock: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl<K: Hash + Eq> Drop for InflightGuard<'_, K> {
+    fn drop(&mut self) {
+        let mut inflight = self.inflight.lock().unwrap();
+        // the map and this guard, the locks are only cloned with the map locked
+        let last = inflight
+            .get(&self.key)
+            .is_some_and(|lock| Arc::ptr_eq(lock, &self.lock) && Arc::strong_count(lock) == 2);
+        if last {
+            inflight.remove(&self.key);
+        }
+    }
+}
+
+// LRU bounded cache with a TTL per entry. concurrent lookups of a missing key are
+// deduplicated: the first caller computes the value while the others wait for it.
+// the locks are never held across an await point.
+pub struct TtlCache<K, V> {
+    name: &'static str, // label of the hit and miss metrics
+    entries: Mutex<SizedCache<K, Entry<V>>>,
+    inflight: Inflight<K>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> TtlCache<K, V> {
+    pub fn new(name: &'static str, capacity: usize) -> Self {
+        TtlCache {
+            name,
+            entries: Mutex::new(SizedCache::with_size(capacity)),
+            inflight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        let expired = match entries.cache_get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => return Some(entry.value.clone()),
+            Some(_) => true,
+            None => false,
+        };
+        if expired {
+            entries.cache_remove(key);
+        }
+        None
+    }
+
+    pub fn insert(&self, key: K, value: V, ttl: Duration) {
+        let entry = Entry {
+            value,
+            expires_at: Instant::now() + ttl,
+        };
+        self.entries.lock().unwrap().cache_set(key, entry);
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        self.entries
+            .lock()
+            .unwrap()
+            .cache_remove(key)
+            .map(|entry| entry.value)
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().cache_clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().cache_size()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // return the cached value or compute it with `f`, only values accepted by `cacheable` are stored.
+    // a lookup served by a concurrent computation counts as a hit.
+    pub async fn get_or_insert_with<F, Fut>(
+        &self,
+        key: K,
+        ttl: Duration,
+        cacheable: impl Fn(&V) -> bool,
+        f: F,
+    ) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        if let Some(value) = self.get(&key) {
+            metrics::observe_cache_lookup(self.name, true);
+            return value;
+        }
+
+        let lock = self
+            .inflight
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(())))
+            .clone();
+        let inflight = InflightGuard {
+            inflight: &self.inflight,
+            key: key.clone(),
+            lock,
+        };
+        let _guard = inflight.lock.lock().await;
+
+        // a concurrent lookup of the same key may have filled the cache while waiting
+        if let Some(value) = self.get(&key) {
+            metrics::observe_cache_lookup(self.name, true);
+            return value;
+        }
+
+        metrics::observe_cache_lookup(self.name, false);
+        let value = f().await;
+        if cacheable(&value) {
+            self.insert(key, value.clone(), ttl);
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn test_cache_key() {
+        let checksum = CacheKey::parse(1, "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap();
+        let lower = CacheKey::parse(1, "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap();
+        let arbitrum = CacheKey::parse(42161, "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap();
+        assert_eq!(checksum, lower);
+        assert_ne!(lower, arbitrum);
+        assert_eq!(lower.to_string(), "1:0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        assert!(CacheKey::parse(1, "0xabc").is_err());
+    }
+
+    #[test]
+    fn test_ttl_and_lru() {
+        let cache: TtlCache<u64, &str> = TtlCache::new("test", 2);
+        cache.insert(1, "a", Duration::from_secs(60));
+        cache.insert(2, "b", Duration::ZERO);
+        assert_eq!(cache.get(&1), Some("a"));
+        assert_eq!(cache.get(&2), None); // expired
+
+        cache.insert(2, "b", Duration::from_secs(60));
+        cache.get(&1); // 1 is now the most recently used
+        cache.insert(3, "c", Duration::from_secs(60));
+        assert_eq!(cache.get(&2), None); // evicted
+        assert_eq!(cache.get(&1), Some("a"));
+        assert_eq!(cache.get(&3), Some("c"));
+        assert_eq!(cache.len(), 2);
+
+        assert_eq!(cache.remove(&1), Some("a"));
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_inflight_dedup() {
+        let cache: Arc<TtlCache<u64, u64>> = Arc::new(TtlCache::new("test-dedup", 10));
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let lookups = (0..8).map(|_| {
+            let cache = cache.clone();
+            let calls = calls.clone();
+            tokio::spawn(async move {
+                cache
+                    .get_or_insert_with(1, Duration::from_secs(60), |_| true, || async {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                        42
+                    })
+                    .await
+            })
+        });
+        for lookup in futures::future::join_all(lookups).await {
+            assert_eq!(lookup.unwrap(), 42);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(cache.inflight.lock().unwrap().is_empty());
+
+        // the lookups that waited for the first one are hits
+        let metrics = metrics::render();
+        assert!(metrics.contains(r#"sampler_cache_lookups_total{cache="test-dedup",result="miss"} 1"#));
+        assert!(metrics.contains(r#"sampler_cache_lookups_total{cache="test-dedup",result="hit"} 7"#));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_lookup() {
+        let cache: TtlCache<u64, u64> = TtlCache::new("test", 10);
+        let slow = |value: u64| async move {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            value
+        };
+
+        // the computing lookup and a waiting one both give up
+        let computing = cache.get_or_insert_with(1, Duration::from_secs(60), |_| true, || slow(1));
+        let waiting = cache.get_or_insert_with(1, Duration::from_secs(60), |_| true, || slow(2));
+        let both = futures::future::join(computing, waiting);
+        assert!(tokio::time::timeout(Duration::from_millis(20), both).await.is_err());
+        assert!(cache.inflight.lock().unwrap().is_empty());
+
+        let value = cache
+            .get_or_insert_with(1, Duration::from_secs(60), |_| true, || async { 3 })
+            .await;
+        assert_eq!(value, 3);
+        assert!(cache.inflight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_uncacheable_values() {
+        let cache: TtlCache<u64, u64> = TtlCache::new("test", 10);
+        let value = cache
+            .get_or_insert_with(1, Duration::from_secs(60), |v| *v != 0, || async { 0 })
+            .await;
+        assert_eq!(value, 0);
+        assert_eq!(cache.get(&1), None);
+    }
+}
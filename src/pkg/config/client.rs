@@ -1,19 +1,113 @@
 use crate::pkg::config::config::ChainConfig;
+use crate::pkg::rpc::failover::FailoverTransport;
+use crate::pkg::rpc::trace::TracedTransport;
+use crate::pkg::telemetry::metrics;
 use alloy::providers::{Provider, ProviderBuilder};
-use eyre::Result;
+use alloy::rpc::client::{BuiltInConnectionString, RpcClient};
+use alloy::rpc::types::{Filter, Log};
+use alloy::transports::{BoxTransport, BoxTransportConnect};
+use eyre::{OptionExt, Result};
 use foundry_block_explorers::Client;
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Instant;
+use tracing::{debug, info_span, warn, Instrument};
+
+// max block range of a single eth_getLogs request
+pub const LOG_SCAN_CHUNK: u64 = 10_000;
 
 pub fn new_scan_client(chain: &ChainConfig) -> Result<Box<Client>> {
-    let client = Client::new(
-        alloy_chains::Chain::from_id(chain.id),
-        chain.etherscan_api_token.clone(),
-    )?;
+    if !chain.has_custom_explorer_api() {
+        let client = Client::new(alloy_chains::Chain::from_id(chain.id), chain.etherscan_api_token.clone())?;
+        return Ok(Box::new(client));
+    }
+
+    let api_url = chain
+        .explorer_api_url()
+        .ok_or_eyre(format!("no explorer API configured for chain {}", chain.name))?;
+    let mut builder = Client::builder()
+        .with_url(chain.block_explorer.as_str())?
+        .with_api_url(api_url.as_str())?;
+    // blockscout and routescan take requests without a key
+    if !chain.etherscan_api_token.is_empty() {
+        builder = builder.with_api_key(chain.etherscan_api_token.clone());
+    }
 
-    Ok(Box::new(client))
+    Ok(Box::new(builder.build()?))
 }
 
 pub async fn new_rpc_client(chain: &ChainConfig) -> Result<Box<dyn Provider>> {
-    let provider = ProviderBuilder::new().on_builtin(&chain.rpc).await?;
+    connect_rpc(chain, None).await
+}
+
+// like new_rpc_client, but the storage reads and calls need rpc_quorum endpoints to agree
+pub async fn new_quorum_rpc_client(chain: &ChainConfig) -> Result<Box<dyn Provider>> {
+    connect_rpc(chain, chain.rpc_quorum.filter(|quorum| *quorum > 1)).await
+}
 
+async fn connect_rpc(chain: &ChainConfig, quorum: Option<usize>) -> Result<Box<dyn Provider>> {
+    // a single endpoint has nothing to fail over to
+    let (transport, is_local) = if chain.rpc_endpoints.is_empty() && quorum.is_none() {
+        let connect: BuiltInConnectionString = chain.rpc.parse()?;
+        (connect.connect_boxed().await?, connect.is_local())
+    } else {
+        (BoxTransport::new(FailoverTransport::connect(chain, quorum).await?), false)
+    };
+
+    let transport = BoxTransport::new(TracedTransport::new(&chain.name, transport));
+    let provider = ProviderBuilder::new().on_client(RpcClient::new(transport, is_local));
     Ok(Box::new(provider))
 }
+
+// an explorer API call in an `explorer` span, logged and counted in the metrics with its latency and outcome
+pub async fn traced_explorer_call<T, E: Display>(
+    chain: &ChainConfig,
+    action: &str,
+    call: impl Future<Output = std::result::Result<T, E>>,
+) -> std::result::Result<T, E> {
+    let span = info_span!("explorer", chain = %chain.name, action);
+    async move {
+        let started = Instant::now();
+        let result = call.await;
+        let latency = started.elapsed();
+        let latency_ms = latency.as_millis() as u64;
+        let outcome = match &result {
+            Ok(_) => {
+                debug!(latency_ms, outcome = "ok", "explorer call");
+                "ok"
+            }
+            Err(e) => {
+                let error = e.to_string();
+                let outcome = if error.to_lowercase().contains("rate limit") {
+                    "rate_limited"
+                } else {
+                    "error"
+                };
+                warn!(latency_ms, outcome, error = %error, "explorer call failed");
+                outcome
+            }
+        };
+        metrics::observe_explorer_call(&chain.name, action, outcome, latency);
+        result
+    }
+    .instrument(span)
+    .await
+}
+
+// eth_getLogs over [from_block, to_block] split into chunks most providers accept
+pub async fn get_logs_in_chunks(
+    provider: &dyn Provider,
+    filter: Filter,
+    from_block: u64,
+    to_block: u64,
+) -> Result<Vec<Log>> {
+    let mut logs = vec![];
+    let mut start = from_block;
+    while start <= to_block {
+        let end = to_block.min(start + LOG_SCAN_CHUNK - 1);
+        let chunk = filter.clone().from_block(start).to_block(end);
+        logs.extend(provider.get_logs(&chunk).await?);
+        start = end + 1;
+    }
+    Ok(logs)
+}
@@ -1,2 +1,3 @@
 pub mod config;
-pub mod client;
\ No newline at end of file
+pub mod client;
+pub mod store;
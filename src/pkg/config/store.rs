@@ -0,0 +1,218 @@
+use crate::pkg::config::config::{Config, CONFIG_CONTENT_ENV};
+use eyre::{eyre, Result};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+// how often the watcher looks at the modification time of the config file
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+// the live config of the server. handlers take a snapshot with current(), so a reload
+// never changes the config under an in-flight request. the per-chain clients are built
+// from the snapshot on every request and pick up a new rpc or explorer on the next one.
+pub struct ConfigStore {
+    path: PathBuf,
+    from_env: bool, // loaded from CONFIG_CONTENT, which a running process never sees change
+    current: RwLock<Arc<Config>>,
+}
+
+// chain names touched by a reload
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct ReloadReport {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+    pub restart_required: bool, // the [server] or [log] section changed, they only apply on restart
+}
+
+impl ConfigStore {
+    pub fn new<P: AsRef<Path>>(path: P, config: Config) -> Self {
+        ConfigStore {
+            path: path.as_ref().to_path_buf(),
+            from_env: false,
+            current: RwLock::new(Arc::new(config)),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let config = Config::load(&path)?;
+        let mut store = Self::new(path, config);
+        store.from_env = Config::is_from_env();
+        Ok(store)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn current(&self) -> Arc<Config> {
+        self.current.read().unwrap().clone()
+    }
+
+    // load and validate the file again. the running config is kept when that fails.
+    pub fn reload(&self) -> Result<ReloadReport> {
+        if self.from_env {
+            return Err(eyre!(
+                "the config comes from {}, restart with the new content to change it",
+                CONFIG_CONTENT_ENV
+            ));
+        }
+        let config = Config::load(&self.path)?;
+        Ok(self.replace(config))
+    }
+
+    pub fn replace(&self, config: Config) -> ReloadReport {
+        let mut current = self.current.write().unwrap();
+        let report = ReloadReport::new(&current, &config);
+        *current = Arc::new(config);
+        report
+    }
+
+    // reload whenever the modification time of the file changes. there is no file to watch
+    // when the config comes from CONFIG_CONTENT.
+    pub fn watch(self: Arc<Self>, interval: Duration) {
+        if self.from_env {
+            info!(env = CONFIG_CONTENT_ENV, "config comes from the environment, not watching {}", self.path.display());
+            return;
+        }
+        tokio::spawn(async move {
+            let mut last_modified = modified(&self.path);
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let modified = modified(&self.path);
+                if modified.is_none() || modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+                let _ = self.reload_and_log("file change");
+            }
+        });
+    }
+
+    // reload on SIGHUP
+    #[cfg(unix)]
+    pub fn reload_on_sighup(self: Arc<Self>) -> Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangup = signal(SignalKind::hangup())?;
+        tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                let _ = self.reload_and_log("SIGHUP");
+            }
+        });
+        Ok(())
+    }
+
+    pub fn reload_and_log(&self, trigger: &str) -> Result<ReloadReport, String> {
+        match self.reload() {
+            Ok(report) => {
+                info!(path = %self.path.display(), trigger, ?report, "config reloaded");
+                Ok(report)
+            }
+            Err(e) => {
+                warn!(path = %self.path.display(), trigger, error = %e, "config not reloaded, keeping the running one");
+                Err(e.to_string())
+            }
+        }
+    }
+}
+
+impl ReloadReport {
+    fn new(old: &Config, new: &Config) -> Self {
+        let mut report = ReloadReport {
+            restart_required: old.server != new.server || old.log != new.log,
+            ..Default::default()
+        };
+        for chain in &new.chains {
+            match old.chains.iter().find(|old_chain| old_chain.name == chain.name) {
+                None => report.added.push(chain.name.clone()),
+                Some(old_chain) if old_chain != chain => report.changed.push(chain.name.clone()),
+                Some(_) => {}
+            }
+        }
+        for chain in &old.chains {
+            if !new.chains.iter().any(|new_chain| new_chain.name == chain.name) {
+                report.removed.push(chain.name.clone());
+            }
+        }
+        report
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(chains: &[(&str, u64, &str)]) -> Config {
+        let chains: Vec<String> = chains
+            .iter()
+            .map(|(name, id, rpc)| {
+                format!(
+                    r#"
+                    [[chains]]
+                    id = {}
+                    name = "{}"
+                    alias = "{}"
+                    block_explorer = "https://etherscan.io"
+                    etherscan_api_token = "token"
+                    rpc = "{}"
+                    "#,
+                    id, name, name, rpc
+                )
+            })
+            .collect();
+        toml::from_str(&chains.join("\n")).unwrap()
+    }
+
+    #[test]
+    fn test_replace() {
+        let store = ConfigStore::new(
+            "config.toml",
+            config(&[("ethereum", 1, "https://eth.example"), ("optimism", 10, "https://op.example")]),
+        );
+        let snapshot = store.current();
+
+        let report = store.replace(config(&[
+            ("ethereum", 1, "https://eth2.example"),
+            ("arbitrum", 42161, "https://arb.example"),
+        ]));
+        assert_eq!(
+            report,
+            ReloadReport {
+                added: vec!["arbitrum".to_string()],
+                removed: vec!["optimism".to_string()],
+                changed: vec!["ethereum".to_string()],
+                restart_required: false,
+            }
+        );
+
+        // the snapshot of an in-flight request keeps the old chains
+        assert_eq!(snapshot.chain_by_name("ethereum").unwrap().rpc, "https://eth.example");
+        assert!(snapshot.chain_by_name("arbitrum").is_err());
+        assert_eq!(store.current().chain_by_name("ethereum").unwrap().rpc, "https://eth2.example");
+    }
+
+    #[test]
+    fn test_reload_refused_with_config_content() {
+        let mut store = ConfigStore::new("config/production.example.toml", config(&[("ethereum", 1, "https://eth.example")]));
+        store.from_env = true;
+        let error = store.reload_and_log("test").unwrap_err();
+        assert!(error.contains("CONFIG_CONTENT"), "{}", error);
+        assert_eq!(store.current().chain_by_name("ethereum").unwrap().rpc, "https://eth.example");
+    }
+
+    #[test]
+    fn test_failed_reload_keeps_config() {
+        let store = ConfigStore::new("config/missing.toml", config(&[("ethereum", 1, "https://eth.example")]));
+        assert!(store.reload_and_log("test").is_err());
+        assert!(store.current().chain_by_name("ethereum").is_ok());
+    }
+}
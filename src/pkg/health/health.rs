@@ -0,0 +1,204 @@
+use crate::pkg::config::client::{new_rpc_client, new_scan_client, traced_explorer_call};
+use crate::pkg::config::config::{redact_url, ChainConfig, Config};
+use alloy::primitives::Address;
+use futures::future::join_all;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// a backend that takes longer to answer counts as down
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+// the orchestrator polls every few seconds, the probes would eat into the explorer rate limits
+const READINESS_CACHE_TTL: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    // last report and the config it probed, a reload probes the new chains right away
+    static ref LAST_REPORT: Mutex<Option<(Arc<Config>, Instant, ReadinessReport)>> = Mutex::new(None);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Failed,
+    Skipped, // the chain has no explorer API configured
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Check {
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain_id: Option<u64>, // as reported by the rpc
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChainReadiness {
+    pub name: String,
+    pub id: u64,
+    pub ready: bool,
+    pub rpc: Check,
+    pub explorer: Check,
+}
+
+// ready when every configured chain is ready
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub chains: Vec<ChainReadiness>,
+}
+
+impl Check {
+    fn new(status: CheckStatus) -> Self {
+        Check {
+            status,
+            latency_ms: None,
+            block_number: None,
+            chain_id: None,
+            error: None,
+        }
+    }
+
+    fn failed(error: String) -> Self {
+        Check {
+            error: Some(error),
+            ..Check::new(CheckStatus::Failed)
+        }
+    }
+}
+
+// probe every chain of the config, at most once per READINESS_CACHE_TTL
+pub async fn readiness(config: Arc<Config>) -> ReadinessReport {
+    if let Some((probed, at, report)) = LAST_REPORT.lock().unwrap().as_ref() {
+        if Arc::ptr_eq(probed, &config) && at.elapsed() < READINESS_CACHE_TTL {
+            return report.clone();
+        }
+    }
+
+    let chains = join_all(config.chains.iter().map(probe_chain)).await;
+    let report = ReadinessReport {
+        ready: chains.iter().all(|chain| chain.ready),
+        chains,
+    };
+    *LAST_REPORT.lock().unwrap() = Some((config, Instant::now(), report.clone()));
+    report
+}
+
+// the rpc has to answer with the configured chain id, the explorer API has to answer
+// when the chain has one
+pub async fn probe_chain(chain: &ChainConfig) -> ChainReadiness {
+    let (rpc, explorer) = tokio::join!(probe_rpc(chain), probe_explorer(chain));
+    ChainReadiness {
+        name: chain.name.clone(),
+        id: chain.id,
+        ready: rpc.status == CheckStatus::Ok && explorer.status != CheckStatus::Failed,
+        rpc,
+        explorer,
+    }
+}
+
+async fn probe_rpc(chain: &ChainConfig) -> Check {
+    let started = Instant::now();
+    let probe = async {
+        let provider = new_rpc_client(chain).await?;
+        let block_number = provider.get_block_number().await?;
+        let chain_id = provider.get_chain_id().await?;
+        Ok::<_, eyre::Report>((block_number, chain_id))
+    };
+
+    let mut check = match tokio::time::timeout(PROBE_TIMEOUT, probe).await {
+        Ok(Ok((block_number, chain_id))) => {
+            let mut check = Check::new(CheckStatus::Ok);
+            if chain_id != chain.id {
+                check = Check::failed(format!("rpc serves chain id {}, configured {}", chain_id, chain.id));
+            }
+            check.block_number = Some(block_number);
+            check.chain_id = Some(chain_id);
+            check
+        }
+        Ok(Err(e)) => Check::failed(redact_error(chain, &e.to_string())),
+        Err(_) => Check::failed(format!("timed out after {:?}", PROBE_TIMEOUT)),
+    };
+    check.latency_ms = Some(started.elapsed().as_millis() as u64);
+    check
+}
+
+async fn probe_explorer(chain: &ChainConfig) -> Check {
+    if !chain.has_explorer_api() {
+        return Check::new(CheckStatus::Skipped);
+    }
+
+    let started = Instant::now();
+    let probe = async {
+        let scan = new_scan_client(chain)?;
+        // the cheapest call that needs a valid api key
+        traced_explorer_call(chain, "balance", scan.get_ether_balance_single(&Address::ZERO, None)).await?;
+        Ok::<_, eyre::Report>(())
+    };
+
+    let mut check = match tokio::time::timeout(PROBE_TIMEOUT, probe).await {
+        Ok(Ok(())) => Check::new(CheckStatus::Ok),
+        Ok(Err(e)) => Check::failed(redact_error(chain, &e.to_string())),
+        Err(_) => Check::failed(format!("timed out after {:?}", PROBE_TIMEOUT)),
+    };
+    check.latency_ms = Some(started.elapsed().as_millis() as u64);
+    check
+}
+
+// the errors of the http clients quote the url, which may carry the rpc or the explorer key
+fn redact_error(chain: &ChainConfig, error: &str) -> String {
+    let mut error = error.to_string();
+    for endpoint in chain.all_rpc_endpoints() {
+        error = error.replace(&endpoint.url, &redact_url(&endpoint.url));
+    }
+    if !chain.etherscan_api_token.is_empty() {
+        error = error.replace(&chain.etherscan_api_token, "***");
+    }
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkg::mock::server::MockServer;
+
+    #[tokio::test]
+    async fn test_probe_chain_offline() {
+        // tests/fixtures/health.json: block 100 of chain 1 and an explorer that answers
+        let server = MockServer::start(1, "health").await.expect("mock server not started");
+        let readiness = probe_chain(&server.chain()).await;
+        assert!(readiness.ready, "{:?}", readiness);
+        assert_eq!(readiness.rpc.block_number, Some(100));
+        assert_eq!(readiness.rpc.chain_id, Some(1));
+        assert_eq!(readiness.explorer.status, CheckStatus::Ok);
+
+        // the rpc of another chain
+        let mut chain = server.chain();
+        chain.id = 10;
+        let readiness = probe_chain(&chain).await;
+        assert!(!readiness.ready);
+        assert_eq!(readiness.rpc.status, CheckStatus::Failed);
+        assert_eq!(readiness.rpc.error.as_deref(), Some("rpc serves chain id 1, configured 10"));
+    }
+
+    #[tokio::test]
+    async fn test_probe_failures_offline() {
+        // erc20_token.json has no explorer answer to the balance probe
+        let server = MockServer::start(1, "erc20_token").await.expect("mock server not started");
+        let mut chain = server.chain();
+        // nothing listens on port 1
+        chain.rpc = "http://127.0.0.1:1/secret-key-0123456789".to_string();
+
+        let readiness = probe_chain(&chain).await;
+        assert!(!readiness.ready);
+        assert_eq!(readiness.rpc.status, CheckStatus::Failed);
+        assert!(!readiness.rpc.error.unwrap().contains("secret-key-0123456789"));
+        assert_eq!(readiness.explorer.status, CheckStatus::Failed);
+    }
+}
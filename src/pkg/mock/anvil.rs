@@ -0,0 +1,263 @@
+use crate::pkg::config::config::{ChainConfig, ExplorerFlavour};
+use crate::pkg::mock::contracts;
+use actix_web::dev::ServerHandle;
+use actix_web::{web, App, HttpResponse, HttpServer};
+use alloy::eips::BlockNumberOrTag;
+use alloy::node_bindings::{Anvil, AnvilInstance};
+use alloy::primitives::{Address, Bytes, FixedBytes, B256, U256};
+use alloy::providers::{Provider, RootProvider};
+use alloy::rpc::types::{TransactionInput, TransactionRequest};
+use alloy::transports::http::{Client, Http};
+use eyre::{OptionExt, Result};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+
+// explorer API stand-in: txlist is built from the blocks of the node, getabi answers
+// with the ABIs registered by the test
+struct Explorer {
+    rpc: String,
+    abis: Mutex<HashMap<Address, String>>,
+}
+
+// local anvil node the sample contracts are installed on with anvil_setCode/anvil_setStorageAt,
+// plus the explorer stand-in. needs the anvil binary of foundry on PATH.
+pub struct AnvilHarness {
+    anvil: AnvilInstance,
+    provider: RootProvider<Http<Client>>,
+    explorer: web::Data<Explorer>,
+    explorer_addr: SocketAddr,
+    explorer_handle: ServerHandle,
+}
+
+impl AnvilHarness {
+    pub async fn start() -> Result<Self> {
+        let anvil = Anvil::new().try_spawn()?;
+        let provider = RootProvider::new_http(anvil.endpoint_url());
+
+        let explorer = web::Data::new(Explorer {
+            rpc: anvil.endpoint(),
+            abis: Mutex::new(HashMap::new()),
+        });
+        let state = explorer.clone();
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(state.clone())
+                .route("/api", web::get().to(explorer_handler))
+                .route("/api/", web::get().to(explorer_handler))
+        })
+        .workers(1)
+        .disable_signals()
+        .bind(("127.0.0.1", 0))?;
+        let explorer_addr = *server.addrs().first().ok_or_eyre("explorer stand-in not bound")?;
+
+        let server = server.run();
+        let explorer_handle = server.handle();
+        tokio::spawn(server);
+
+        Ok(AnvilHarness {
+            anvil,
+            provider,
+            explorer,
+            explorer_addr,
+            explorer_handle,
+        })
+    }
+
+    // chain config pointing at the node and the explorer stand-in
+    pub fn chain(&self) -> ChainConfig {
+        ChainConfig {
+            id: self.anvil.chain_id(),
+            name: "anvil".to_string(),
+            alias: "Anvil".to_string(),
+            aliases: vec![],
+            block_explorer: format!("http://{}", self.explorer_addr),
+            etherscan_api_token: "anvil".to_string(),
+            etherscan_api_url: Some(format!("http://{}/api", self.explorer_addr)),
+            explorer_flavour: ExplorerFlavour::Etherscan,
+            rpc: self.anvil.endpoint(),
+            rpc_endpoints: vec![],
+            rpc_quorum: None,
+            rpc_timeout: None,
+            proxy_cache_ttl: None,
+            trace: true,
+            archive: true,
+        }
+    }
+
+    // unlocked accounts of the node
+    pub fn accounts(&self) -> &[Address] {
+        self.anvil.addresses()
+    }
+
+    pub async fn set_code(&self, address: Address, code: Bytes) -> Result<()> {
+        self.provider
+            .raw_request::<_, Value>("anvil_setCode".into(), (address, code))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_storage(&self, address: Address, slot: &str, value: B256) -> Result<()> {
+        let slot = U256::from_str_radix(slot.trim_start_matches("0x"), 16)?;
+        self.provider
+            .raw_request::<_, Value>("anvil_setStorageAt".into(), (address, slot, value))
+            .await?;
+        Ok(())
+    }
+
+    // verified source of the address as far as the explorer stand-in is concerned
+    pub fn register_abi(&self, address: Address, abi: &str) {
+        self.explorer.abis.lock().unwrap().insert(address, abi.to_string());
+    }
+
+    // send a call from an unlocked account and wait until it is mined
+    pub async fn send(&self, from: Address, to: Address, input: Bytes) -> Result<B256> {
+        let tx = TransactionRequest::default()
+            .from(from)
+            .to(to)
+            .input(TransactionInput::new(input));
+        let receipt = self.provider.send_transaction(tx).await?.get_receipt().await?;
+        Ok(receipt.transaction_hash)
+    }
+
+    // the sample contract, every call emits Ping(msg.sender)
+    pub async fn deploy_plain(&self, address: Address) -> Result<()> {
+        self.set_code(address, contracts::plain()).await?;
+        self.register_abi(address, contracts::PING_ABI);
+        Ok(())
+    }
+
+    pub async fn deploy_eip_1967_proxy(&self, proxy: Address, implementation: Address) -> Result<()> {
+        self.set_code(proxy, contracts::eip_1967_proxy()).await?;
+        self.set_storage(proxy, contracts::EIP_1967_LOGIC_SLOT, implementation.into_word())
+            .await
+    }
+
+    pub async fn deploy_beacon_proxy(&self, proxy: Address, beacon: Address, implementation: Address) -> Result<()> {
+        self.set_code(beacon, contracts::beacon(implementation)).await?;
+        self.set_code(proxy, contracts::beacon_proxy()).await?;
+        self.set_storage(proxy, contracts::EIP_1967_BEACON_SLOT, beacon.into_word())
+            .await
+    }
+
+    pub async fn deploy_clone(&self, clone: Address, implementation: Address) -> Result<()> {
+        self.set_code(clone, contracts::clone(implementation)).await
+    }
+
+    pub async fn deploy_diamond(&self, diamond: Address, facets: &[(Address, Vec<FixedBytes<4>>)]) -> Result<()> {
+        self.set_code(diamond, contracts::diamond(facets)).await
+    }
+}
+
+impl Drop for AnvilHarness {
+    fn drop(&mut self) {
+        // the node itself is killed when the AnvilInstance is dropped
+        drop(self.explorer_handle.stop(false));
+    }
+}
+
+impl Explorer {
+    async fn answer(&self, query: &BTreeMap<String, String>) -> Result<Value> {
+        let param = |key: &str| query.get(key).map(String::as_str).unwrap_or_default();
+        match (param("module"), param("action")) {
+            ("account", "txlist") => self.txlist(query).await,
+            ("contract", "getabi") => {
+                let address = param("address").parse::<Address>()?;
+                Ok(match self.abis.lock().unwrap().get(&address) {
+                    Some(abi) => json!({ "status": "1", "message": "OK", "result": abi }),
+                    None => json!({
+                        "status": "0",
+                        "message": "NOTOK",
+                        "result": "Contract source code not verified"
+                    }),
+                })
+            }
+            (module, action) => Ok(json!({
+                "status": "0",
+                "message": "NOTOK",
+                "result": format!("{}.{} is not supported by the stand-in", module, action)
+            })),
+        }
+    }
+
+    // transactions from or to the address in Etherscan's txlist format
+    async fn txlist(&self, query: &BTreeMap<String, String>) -> Result<Value> {
+        let address = query.get("address").ok_or_eyre("address missing")?.parse::<Address>()?;
+        let provider = RootProvider::<Http<Client>>::new_http(self.rpc.parse()?);
+        let latest = provider.get_block_number().await?;
+        let number = |key: &str| query.get(key).and_then(|value| value.parse::<u64>().ok());
+        let start_block = number("startblock").unwrap_or(0);
+        let end_block = number("endblock").unwrap_or(latest).min(latest);
+
+        let mut transactions = vec![];
+        for block_number in start_block..=end_block {
+            let Some(block) = provider
+                .get_block_by_number(BlockNumberOrTag::Number(block_number), true)
+                .await?
+            else {
+                continue;
+            };
+            for tx in block.transactions.as_transactions().unwrap_or_default() {
+                if tx.to != Some(address) && tx.from != address {
+                    continue;
+                }
+                let receipt = provider
+                    .get_transaction_receipt(tx.hash)
+                    .await?
+                    .ok_or_eyre("receipt not found")?;
+                let method_id = if tx.input.len() >= 4 {
+                    format!("{}", Bytes::copy_from_slice(&tx.input[..4]))
+                } else {
+                    "0x".to_string()
+                };
+                transactions.push(json!({
+                    "blockNumber": block_number.to_string(),
+                    "timeStamp": block.header.timestamp.to_string(),
+                    "hash": format!("{:#x}", tx.hash),
+                    "nonce": tx.nonce.to_string(),
+                    "blockHash": tx.block_hash.map(|hash| format!("{:#x}", hash)).unwrap_or_default(),
+                    "transactionIndex": tx.transaction_index.unwrap_or_default().to_string(),
+                    "from": format!("{:#x}", tx.from),
+                    "to": tx.to.map(|to| format!("{:#x}", to)).unwrap_or_default(),
+                    "value": tx.value.to_string(),
+                    "gas": tx.gas.to_string(),
+                    "gasPrice": tx.gas_price.unwrap_or(receipt.effective_gas_price).to_string(),
+                    "isError": if receipt.status() { "0" } else { "1" },
+                    "txreceipt_status": if receipt.status() { "1" } else { "0" },
+                    "input": format!("{}", tx.input),
+                    "contractAddress": receipt.contract_address.map(|a| format!("{:#x}", a)).unwrap_or_default(),
+                    "cumulativeGasUsed": receipt.gas_used.to_string(),
+                    "gasUsed": receipt.gas_used.to_string(),
+                    "confirmations": (latest - block_number + 1).to_string(),
+                    "methodId": method_id,
+                    "functionName": "",
+                }));
+            }
+        }
+
+        if query.get("sort").map(String::as_str) == Some("desc") {
+            transactions.reverse();
+        }
+        // only the first page is served
+        if let Some(offset) = number("offset").filter(|offset| *offset > 0) {
+            transactions.truncate(offset as usize);
+        }
+
+        if transactions.is_empty() {
+            return Ok(json!({ "status": "0", "message": "No transactions found", "result": [] }));
+        }
+        Ok(json!({ "status": "1", "message": "OK", "result": transactions }))
+    }
+}
+
+async fn explorer_handler(
+    state: web::Data<Explorer>,
+    query: web::Query<BTreeMap<String, String>>,
+) -> HttpResponse {
+    let response = match state.answer(&query).await {
+        Ok(response) => response,
+        Err(e) => json!({ "status": "0", "message": "NOTOK", "result": e.to_string() }),
+    };
+    HttpResponse::Ok().json(response)
+}
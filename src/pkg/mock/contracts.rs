@@ -0,0 +1,74 @@
+use alloy::hex;
+use alloy::primitives::{Address, Bytes, FixedBytes};
+use alloy::sol;
+use alloy::sol_types::{SolEvent, SolValue};
+
+// hand assembled runtime bytecode of the sample contracts installed on the anvil node,
+// there is no compiler in the test setup
+
+// the sample contract: any call emits Ping(msg.sender)
+sol! {
+    event Ping(address indexed sender);
+    function ping() external;
+    function pong() external;
+}
+
+pub const PING_ABI: &str = r#"[
+    {"type":"function","name":"ping","inputs":[],"outputs":[],"stateMutability":"nonpayable"},
+    {"type":"function","name":"pong","inputs":[],"outputs":[],"stateMutability":"nonpayable"},
+    {"type":"event","name":"Ping","inputs":[{"name":"sender","type":"address","indexed":true,"internalType":"address"}],"anonymous":false}
+]"#;
+
+pub const EIP_1967_LOGIC_SLOT: &str = "360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc";
+pub const EIP_1967_BEACON_SLOT: &str = "a3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50";
+
+fn code(hex_str: &str) -> Bytes {
+    hex::decode(hex_str).expect("invalid bytecode").into()
+}
+
+// CALLER PUSH32 <Ping> PUSH1 0 PUSH1 0 LOG2 STOP
+pub fn plain() -> Bytes {
+    code(&format!("337f{}60006000a200", hex::encode(Ping::SIGNATURE_HASH)))
+}
+
+// Solady ERC1967 minimal proxy, delegates to the implementation in the EIP-1967 logic slot
+pub fn eip_1967_proxy() -> Bytes {
+    code(&format!(
+        "363d3d373d3d363d7f{}545af43d6000803e6038573d6000fd5b3d6000f3",
+        EIP_1967_LOGIC_SLOT
+    ))
+}
+
+// answers every call, implementation() included, with the implementation address
+pub fn beacon(implementation: Address) -> Bytes {
+    code(&format!("73{}60005260206000f3", hex::encode(implementation)))
+}
+
+// staticcall implementation() on the beacon in the EIP-1967 beacon slot, then delegate to it
+pub fn beacon_proxy() -> Bytes {
+    code(&format!(
+        "635c60da1b60e01b600052\
+         60206000600460007f{}545afa50600051\
+         366000600037\
+         60006000366000845af4\
+         3d600060003e6058573d6000fd5b3d6000f3",
+        EIP_1967_BEACON_SLOT
+    ))
+}
+
+// EIP-1167 minimal proxy
+pub fn clone(implementation: Address) -> Bytes {
+    code(&format!(
+        "363d3d373d3d3d363d73{}5af43d82803e903d91602b57fd5bf3",
+        hex::encode(implementation)
+    ))
+}
+
+// a diamond that only implements the facets() loupe call, its answer is appended to the code.
+// every other call stops without return data.
+pub fn diamond(facets: &[(Address, Vec<FixedBytes<4>>)]) -> Bytes {
+    let loupe = facets.to_vec().abi_encode();
+    // selector == facets() ? codecopy(0, 30, len) and return it : stop
+    let header = format!("60003560e01c637a0ed62714601057005b61{:04x}8061001e6000396000f3", loupe.len());
+    code(&format!("{}{}", header, hex::encode(loupe)))
+}
@@ -0,0 +1,4 @@
+pub mod anvil;
+pub mod contracts;
+pub mod fixture;
+pub mod server;
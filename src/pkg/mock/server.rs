@@ -0,0 +1,217 @@
+use crate::pkg::config::config::{ChainConfig, ExplorerFlavour};
+use crate::pkg::mock::fixture::{empty_account_response, Fixtures};
+use actix_web::dev::ServerHandle;
+use actix_web::{web, App, HttpResponse, HttpServer};
+use eyre::{eyre, OptionExt, Result};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::env;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+
+// when set, requests missing from the fixtures are forwarded to the chain of the same id
+// in the config and the answers are appended to the fixture file
+const RECORD_ENV: &str = "MOCK_RECORD";
+
+// real backends of a recording session, the rpc has to be an http endpoint
+struct Upstream {
+    client: reqwest::Client,
+    rpc: String,
+    explorer_api: String,
+    api_key: String,
+}
+
+struct State {
+    name: String,
+    fixtures: Mutex<Fixtures>,
+    upstream: Option<Upstream>,
+}
+
+// local JSON-RPC (POST /rpc) and Etherscan-compatible (GET /api) server answering
+// from tests/fixtures/<name>.json, stopped when dropped
+pub struct MockServer {
+    addr: SocketAddr,
+    chain_id: u64,
+    handle: ServerHandle,
+}
+
+impl MockServer {
+    pub async fn start(chain_id: u64, name: &str) -> Result<Self> {
+        let upstream = Self::upstream(chain_id)?;
+        let fixtures = match Fixtures::load(name) {
+            Ok(fixtures) => fixtures,
+            // a new fixture file is created by the first recording
+            Err(_) if upstream.is_some() => Fixtures::default(),
+            Err(e) => return Err(eyre!("fixture {} not loaded: {}", name, e)),
+        };
+
+        let state = web::Data::new(State {
+            name: name.to_string(),
+            fixtures: Mutex::new(fixtures),
+            upstream,
+        });
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(state.clone())
+                .route("/rpc", web::post().to(rpc_handler))
+                .route("/api", web::get().to(explorer_handler))
+                .route("/api/", web::get().to(explorer_handler))
+        })
+        .workers(1)
+        .disable_signals()
+        .bind(("127.0.0.1", 0))?;
+        let addr = *server.addrs().first().ok_or_eyre("mock server not bound")?;
+
+        let server = server.run();
+        let handle = server.handle();
+        tokio::spawn(server);
+
+        Ok(MockServer { addr, chain_id, handle })
+    }
+
+    // chain config pointing both the rpc and the explorer API at the mock
+    pub fn chain(&self) -> ChainConfig {
+        ChainConfig {
+            id: self.chain_id,
+            name: "mock".to_string(),
+            alias: "Mock".to_string(),
+            aliases: vec![],
+            block_explorer: format!("http://{}", self.addr),
+            etherscan_api_token: "mock".to_string(),
+            etherscan_api_url: Some(format!("http://{}/api", self.addr)),
+            explorer_flavour: ExplorerFlavour::Etherscan,
+            rpc: format!("http://{}/rpc", self.addr),
+            rpc_endpoints: vec![],
+            rpc_quorum: None,
+            rpc_timeout: None,
+            proxy_cache_ttl: None,
+            trace: false,
+            archive: false,
+        }
+    }
+
+    fn upstream(chain_id: u64) -> Result<Option<Upstream>> {
+        if env::var(RECORD_ENV).is_err() {
+            return Ok(None);
+        }
+        let chain = crate::CONFIG
+            .chains
+            .iter()
+            .find(|chain| chain.id == chain_id)
+            .ok_or_eyre(format!("no chain {} to record from", chain_id))?;
+        let explorer_api = chain
+            .explorer_api_url()
+            .ok_or_eyre(format!("no explorer API known for chain {}", chain_id))?;
+        Ok(Some(Upstream {
+            client: reqwest::Client::new(),
+            rpc: chain.rpc.clone(),
+            explorer_api,
+            api_key: chain.etherscan_api_token.clone(),
+        }))
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        // the stop command is sent right away, no need to wait for the shutdown
+        drop(self.handle.stop(false));
+    }
+}
+
+impl State {
+    async fn rpc(&self, request: Value) -> Value {
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = request.get("method").and_then(Value::as_str).unwrap_or_default().to_string();
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        let recorded = self.fixtures.lock().unwrap().rpc_response(&method, &params);
+        let answer = match (recorded, &self.upstream) {
+            (Some(answer), _) => answer,
+            (None, Some(upstream)) => match self.record_rpc(upstream, &request, &method, &params).await {
+                Ok(answer) => answer,
+                Err(e) => Err(json!({ "code": -32603, "message": format!("recording failed: {}", e) })),
+            },
+            (None, None) => empty_account_response(&method).unwrap_or_else(|| {
+                Err(json!({ "code": -32601, "message": format!("no fixture for {} {}", method, params) }))
+            }),
+        };
+
+        match answer {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+        }
+    }
+
+    async fn record_rpc(
+        &self,
+        upstream: &Upstream,
+        request: &Value,
+        method: &str,
+        params: &Value,
+    ) -> Result<std::result::Result<Value, Value>> {
+        let response: Value = upstream.client.post(&upstream.rpc).json(request).send().await?.json().await?;
+
+        let mut fixtures = self.fixtures.lock().unwrap();
+        fixtures.record_rpc(method, params, &response);
+        fixtures.save(&self.name)?;
+
+        Ok(match response.get("error") {
+            Some(error) => Err(error.clone()),
+            None => Ok(response.get("result").cloned().unwrap_or(Value::Null)),
+        })
+    }
+
+    async fn explorer(&self, query: BTreeMap<String, String>) -> Value {
+        let recorded = self.fixtures.lock().unwrap().explorer_response(&query);
+        match (recorded, &self.upstream) {
+            (Some(response), _) => response,
+            (None, Some(upstream)) => match self.record_explorer(upstream, &query).await {
+                Ok(response) => response,
+                Err(e) => json!({ "status": "0", "message": "NOTOK", "result": format!("recording failed: {}", e) }),
+            },
+            (None, None) => json!({ "status": "0", "message": "NOTOK", "result": format!("no fixture for {:?}", query) }),
+        }
+    }
+
+    async fn record_explorer(&self, upstream: &Upstream, query: &BTreeMap<String, String>) -> Result<Value> {
+        let mut upstream_query = query.clone();
+        upstream_query.insert("apikey".to_string(), upstream.api_key.clone());
+        let response: Value = upstream
+            .client
+            .get(&upstream.explorer_api)
+            .query(&upstream_query)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        let mut fixtures = self.fixtures.lock().unwrap();
+        fixtures.record_explorer(query, &response);
+        fixtures.save(&self.name)?;
+        Ok(response)
+    }
+}
+
+// single and batch JSON-RPC requests
+async fn rpc_handler(state: web::Data<State>, body: web::Json<Value>) -> HttpResponse {
+    let response = match body.into_inner() {
+        Value::Array(requests) => {
+            let mut responses = vec![];
+            for request in requests {
+                responses.push(state.rpc(request).await);
+            }
+            Value::Array(responses)
+        }
+        request => state.rpc(request).await,
+    };
+    HttpResponse::Ok().json(response)
+}
+
+async fn explorer_handler(
+    state: web::Data<State>,
+    query: web::Query<BTreeMap<String, String>>,
+) -> HttpResponse {
+    let mut query = query.into_inner();
+    query.remove("apikey");
+    HttpResponse::Ok().json(state.explorer(query).await)
+}
@@ -1,2 +1,8 @@
+pub mod cache;
 pub mod config;
-pub mod proxy;
\ No newline at end of file
+pub mod health;
+#[cfg(test)]
+pub mod mock;
+pub mod proxy;
+pub mod rpc;
+pub mod telemetry;
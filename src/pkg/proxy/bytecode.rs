@@ -0,0 +1,143 @@
+use alloy::hex;
+use crate::pkg::proxy::proxy::ProxyType;
+use alloy::primitives::Address;
+
+// clones that embed the 20-byte implementation address between a fixed prefix and suffix
+// (proxy type, prefix, suffix), the EIP-1167 layout with PUSH1..PUSH20 is parsed separately
+const CLONE_PATTERNS: [(ProxyType, &str, &str); 5] = [
+    // EIP-3448 MetaProxy, followed by the metadata
+    (
+        ProxyType::Eip3448,
+        "363d3d373d3d3d3d60368038038091363936013d73",
+        "5af43d3d93803e603457fd5bf3",
+    ),
+    // ERC-7511 PUSH0 minimal proxy
+    (ProxyType::Erc7511, "365f5f375f5f365f73", "5af43d5f5f3e5f3d91602a57fd5bf3"),
+    // Solady LibClone PUSH0 clone
+    (ProxyType::SoladyPush0, "5f5f365f5f37365f73", "5af43d5f5f3e6029573d5ffd5b3d5ff3"),
+    // Vyper create_forwarder_to
+    (
+        ProxyType::VyperForwarder,
+        "366000600037611000600036600073",
+        "5af4602c57600080fd5b6110006000f3",
+    ),
+    // 0age more-minimal proxy
+    (ProxyType::ZeroAgeClone, "3d3d3d3d363d3d37363d73", "5af43d3d93803e602a57fd5bf3"),
+];
+
+// PUSH32 <EIP-1967 logic slot> SLOAD
+const ERC1967_SLOT_SLOAD: &str =
+    "7f360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc54";
+// masterCopy() selector answered by the Safe proxy fallback
+const SAFE_MASTER_COPY_SELECTOR: &str = "a619486e";
+// minimal proxies are tiny, anything larger is a full contract that happens to contain the pattern
+const MAX_PROXY_BYTECODE_LEN: usize = 256;
+
+// implementation address embedded in a known clone bytecode
+pub fn parse_clone_bytecode(bytecode: &[u8]) -> Option<(ProxyType, Address)> {
+    let bytecode_str = hex::encode(bytecode);
+    for (proxy_type, prefix, suffix) in CLONE_PATTERNS {
+        let Some(rest) = bytecode_str.strip_prefix(prefix) else {
+            continue;
+        };
+        if rest.len() < 40 || !rest[40..].starts_with(suffix) {
+            continue;
+        }
+        if proxy_type != ProxyType::Eip3448 && rest.len() != 40 + suffix.len() {
+            continue;
+        }
+        let addr = rest[..40].parse::<Address>().ok()?;
+        if addr == Address::ZERO {
+            return None;
+        }
+        return Some((proxy_type, addr));
+    }
+    None
+}
+
+// Solady ERC1967/ERC1967I and similar minimal proxies that load the implementation from the EIP-1967 slot
+pub fn is_erc1967_minimal_proxy(bytecode: &[u8]) -> bool {
+    bytecode.len() <= MAX_PROXY_BYTECODE_LEN && hex::encode(bytecode).contains(ERC1967_SLOT_SLOAD)
+}
+
+// Safe proxies keep the singleton (masterCopy) in storage slot 0
+pub fn is_safe_proxy(bytecode: &[u8]) -> bool {
+    bytecode.len() <= MAX_PROXY_BYTECODE_LEN && hex::encode(bytecode).contains(SAFE_MASTER_COPY_SELECTOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(hex_str: &str) -> Vec<u8> {
+        hex::decode(hex_str).expect("invalid fixture")
+    }
+
+    #[test]
+    fn test_parse_clone_bytecode() {
+        let fixtures = [
+            (
+                ProxyType::Eip3448,
+                "363d3d373d3d3d3d60368038038091363936013d73d9db270c1b5e3bd161e8c8503c55ceabee7095525af43d3d93803e603457fd5bf3000000000000000000000000000000000000000000000000000000000000002a",
+                "0xd9db270c1b5e3bd161e8c8503c55ceabee709552",
+            ),
+            (
+                ProxyType::Erc7511,
+                "365f5f375f5f365f73a0b86991c6218b36c1d19d4a2e9eb0ce3606eb485af43d5f5f3e5f3d91602a57fd5bf3",
+                "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+            ),
+            (
+                ProxyType::SoladyPush0,
+                "5f5f365f5f37365f73c36442b4a4522e871399cd717abdd847ab11fe885af43d5f5f3e6029573d5ffd5b3d5ff3",
+                "0xc36442b4a4522e871399cd717abdd847ab11fe88",
+            ),
+            (
+                ProxyType::VyperForwarder,
+                "3660006000376110006000366000732157a7894439191e520825fe9399ab8655e0f7085af4602c57600080fd5b6110006000f3",
+                "0x2157a7894439191e520825fe9399ab8655e0f708",
+            ),
+            (
+                ProxyType::ZeroAgeClone,
+                "3d3d3d3d363d3d37363d7300000000000000000000000000000000000000015af43d3d93803e602a57fd5bf3",
+                "0x0000000000000000000000000000000000000001",
+            ),
+        ];
+
+        for (proxy_type, bytecode, implementation) in fixtures {
+            let (detected, addr) = parse_clone_bytecode(&code(bytecode))
+                .unwrap_or_else(|| panic!("{} not detected", proxy_type));
+            assert_eq!(detected, proxy_type);
+            assert_eq!(addr, implementation.parse::<Address>().unwrap());
+        }
+    }
+
+    #[test]
+    fn test_parse_clone_bytecode_rejects() {
+        // EIP-1167 is handled by the dedicated parser
+        let eip_1167 = "363d3d373d3d3d363d73bebebebebebebebebebebebebebebebebebebebe5af43d82803e903d91602b57fd5bf3";
+        assert!(parse_clone_bytecode(&code(eip_1167)).is_none());
+
+        // trailing bytes are only allowed for the metadata of EIP-3448
+        let padded = "365f5f375f5f365f73a0b86991c6218b36c1d19d4a2e9eb0ce3606eb485af43d5f5f3e5f3d91602a57fd5bf300";
+        assert!(parse_clone_bytecode(&code(padded)).is_none());
+
+        // zero implementation
+        let zero = "3d3d3d3d363d3d37363d7300000000000000000000000000000000000000005af43d3d93803e602a57fd5bf3";
+        assert!(parse_clone_bytecode(&code(zero)).is_none());
+
+        assert!(parse_clone_bytecode(&[]).is_none());
+    }
+
+    #[test]
+    fn test_slot_based_proxies() {
+        // Solady LibClone ERC1967 minimal proxy
+        let solady_erc1967 = "363d3d373d3d363d7f360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc545af43d6000803e6038573d6000fd5b3d6000f3";
+        assert!(is_erc1967_minimal_proxy(&code(solady_erc1967)));
+        assert!(!is_safe_proxy(&code(solady_erc1967)));
+
+        // Safe proxy 1.3.0
+        let safe = "608060405273ffffffffffffffffffffffffffffffffffffffff600054167fa619486e0000000000000000000000000000000000000000000000000000000060003514156050578060005260206000f35b3660008037600080366000845af43d6000803e60008114156070573d6000fd5b3d6000f3fea2646970667358221220d1429297349653a4918076d650332de1a1068c5f3e07c5c82360c277770b955264736f6c63430007060033";
+        assert!(is_safe_proxy(&code(safe)));
+        assert!(!is_erc1967_minimal_proxy(&code(safe)));
+    }
+}
@@ -1 +1,2 @@
-pub mod proxy;
\ No newline at end of file
+pub mod bytecode;
+pub mod proxy;
@@ -1,18 +1,41 @@
-use crate::pkg::config::client::new_rpc_client;
+use crate::pkg::cache::cache::{CacheKey, TtlCache};
+use crate::pkg::config::client::{get_logs_in_chunks, new_quorum_rpc_client, new_scan_client, traced_explorer_call};
 use crate::pkg::config::config;
+use crate::pkg::proxy::bytecode;
+use crate::pkg::telemetry::metrics;
+use alloy::eips::BlockId;
 use alloy::providers::Provider;
-use alloy::rpc::types::TransactionInput;
+use alloy::rpc::types::{Filter, Log, TransactionInput};
+use alloy::sol;
+use alloy::sol_types::{SolCall, SolEvent};
+use alloy::transports::{RpcError, TransportError};
 use alloy::{primitives::*, rpc::types::TransactionRequest};
-use eyre::{eyre, Result};
+use cached::{Cached, SizedCache};
+use eyre::{eyre, OptionExt, Result};
 use futures::join;
 use std::boxed::Box;
 use std::str::FromStr;
 use lazy_static::lazy_static;
 use std::collections::HashMap;
 use std::sync::Mutex;
+use std::time::Duration;
+use tracing::instrument;
+
+// cache sizes and the default ttl of the latest-block detection results
+const PROXY_CACHE_SIZE: usize = 10_000;
+const HISTORY_CACHE_SIZE: usize = 1_000;
+const DEFAULT_PROXY_CACHE_TTL: u64 = 3600;
+// a DiamondCut replay scans at most this many blocks after the deployment
+const MAX_DIAMOND_CUT_SCAN_BLOCKS: u64 = 1_000_000;
+// a historical detection looks for the next upgrade at most this many blocks ahead
+const MAX_PERIOD_SCAN_BLOCKS: u64 = 100_000;
 
 lazy_static! {
-    static ref CACHE: Mutex<HashMap<String, DetectResult>> = Mutex::new(HashMap::new());
+    // detection result at the latest block
+    static ref CACHE: TtlCache<CacheKey, DetectResult> = TtlCache::new("proxy", PROXY_CACHE_SIZE);
+    // detection results of historical block ranges, the past never changes
+    static ref HISTORY_CACHE: Mutex<SizedCache<CacheKey, Vec<RangeEntry>>> =
+        Mutex::new(SizedCache::with_size(HISTORY_CACHE_SIZE));
 }
 
 // contants
@@ -24,6 +47,43 @@ const OPEN_ZEPPELIN_IMPLEMENTATION_SLOT: &str =
     "0x7050c9e0f4ca769c69bd3a8ef740bc37934f8e2c036e5a723fd8ee048ed3f8c3";
 const EIP_1822_LOGIC_SLOT: &str =
     "0xc5f16f0fcc639fa48a6947836d9850f504798523bf8c9a3a87d5876cf622bcf7";
+const EIP_1967_ADMIN_SLOT: &str =
+    "0xb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103";
+const GNOSIS_SAFE_MASTER_COPY_SLOT: &str =
+    "0x0000000000000000000000000000000000000000000000000000000000000000";
+
+// EIP-2535 diamond loupe and cut
+sol! {
+    struct Facet {
+        address facetAddress;
+        bytes4[] functionSelectors;
+    }
+
+    struct FacetCut {
+        address facetAddress;
+        uint8 action;
+        bytes4[] functionSelectors;
+    }
+
+    function facets() external view returns (Facet[] memory facets_);
+    function facetAddress(bytes4 _functionSelector) external view returns (address facetAddress_);
+    function facetAddresses() external view returns (address[] memory facetAddresses_);
+    function facetFunctionSelectors(address _facet) external view returns (bytes4[] memory facetFunctionSelectors_);
+    function supportsInterface(bytes4 interfaceId) external view returns (bool);
+
+    event DiamondCut(FacetCut[] _diamondCut, address _init, bytes _calldata);
+}
+
+// ERC-165 interface id of IDiamondLoupe
+const DIAMOND_LOUPE_INTERFACE_ID: [u8; 4] = [0x48, 0xe2, 0xb0, 0x93];
+
+// upgrade events
+sol! {
+    event Upgraded(address indexed implementation); // EIP-1967 proxies and beacons, OpenZeppelin
+    event BeaconUpgraded(address indexed beacon); // EIP-1967 beacon proxies
+    event NewImplementation(address oldImplementation, address newImplementation); // Compound
+    event ChangedMasterCopy(address masterCopy); // Safe before 1.3.0
+}
 
 // methods
 const EIP_1167_BEACON_METHODS: [&str; 2] = [
@@ -42,50 +102,432 @@ const EIP_1167_BYTECODE_PREFIX: &str = "0x363d3d373d3d3d363d";
 const EIP_1167_BYTECODE_SUFFIX: &str = "57fd5bf3";
 
 // proxy types
-#[derive(Debug)]
-enum ProxyType {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProxyType {
     Eip1167,
+    Eip1967Logic,
     Eip1967Beacon,
     OpenZeppelin,
     Eip1822,
     Eip897,
     GnosisSafe,
+    GnosisSafeSlot,
     Compound,
+    Eip3448,
+    Erc7511,
+    SoladyPush0,
+    VyperForwarder,
+    ZeroAgeClone,
+    Erc1967Minimal,
+    Eip2535,
+}
+
+impl ProxyType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProxyType::Eip1167 => "eip_1167",
+            ProxyType::Eip1967Logic => "eip_1967_logic",
+            ProxyType::Eip1967Beacon => "eip_1967_beacon",
+            ProxyType::OpenZeppelin => "open_zeppelin",
+            ProxyType::Eip1822 => "eip_1822",
+            ProxyType::Eip897 => "eip_897",
+            ProxyType::GnosisSafe => "gnosis_safe",
+            ProxyType::GnosisSafeSlot => "gnosis_safe_slot",
+            ProxyType::Compound => "compound",
+            ProxyType::Eip3448 => "eip_3448",
+            ProxyType::Erc7511 => "erc_7511",
+            ProxyType::SoladyPush0 => "solady_push0",
+            ProxyType::VyperForwarder => "vyper_forwarder",
+            ProxyType::ZeroAgeClone => "0age_clone",
+            ProxyType::Erc1967Minimal => "erc1967_minimal",
+            ProxyType::Eip2535 => "eip_2535",
+        }
+    }
+
+    fn upgrade_tracking(&self) -> UpgradeTracking {
+        match self {
+            ProxyType::Eip1167
+            | ProxyType::Eip3448
+            | ProxyType::Erc7511
+            | ProxyType::SoladyPush0
+            | ProxyType::VyperForwarder
+            | ProxyType::ZeroAgeClone => UpgradeTracking::Immutable,
+            // a beacon proxy follows the Upgraded events of its beacon
+            ProxyType::Eip1967Logic
+            | ProxyType::Erc1967Minimal
+            | ProxyType::OpenZeppelin
+            | ProxyType::Eip1967Beacon => UpgradeTracking::Event(Upgraded::SIGNATURE_HASH, true),
+            ProxyType::Compound => UpgradeTracking::Event(NewImplementation::SIGNATURE_HASH, true),
+            // later Safes switch the singleton by a delegatecall to a migration contract
+            ProxyType::GnosisSafe | ProxyType::GnosisSafeSlot => {
+                UpgradeTracking::Event(ChangedMasterCopy::SIGNATURE_HASH, false)
+            }
+            ProxyType::Eip1822 | ProxyType::Eip897 | ProxyType::Eip2535 => UpgradeTracking::Untracked,
+        }
+    }
+}
+
+impl std::fmt::Display for ProxyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+// how the upgrades of a proxy type show up on chain
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UpgradeTracking {
+    Immutable,         // the target is part of the bytecode and never changes
+    Event(B256, bool), // topic of the upgrade event, and whether every upgrade emits it
+    Untracked,         // no event, only the state at a block tells the implementation
+}
+
+// new implementation of an upgrade event, None for any other log
+fn upgraded_implementation(log: &Log) -> Option<Address> {
+    let topic = *log.topic0()?;
+    let implementation = if topic == Upgraded::SIGNATURE_HASH {
+        Upgraded::decode_log_data(log.data(), true).ok()?.implementation
+    } else if topic == NewImplementation::SIGNATURE_HASH {
+        NewImplementation::decode_log_data(log.data(), true).ok()?.newImplementation
+    } else if topic == ChangedMasterCopy::SIGNATURE_HASH {
+        ChangedMasterCopy::decode_log_data(log.data(), true).ok()?.masterCopy
+    } else {
+        return None;
+    };
+    (implementation != Address::ZERO).then_some(implementation)
+}
+
+// a detection strategy that found a target
+#[derive(Debug, Clone, PartialEq)]
+pub struct StrategyMatch {
+    pub proxy_type: ProxyType,
+    pub target: Address,
+}
+
+// what a single detection strategy found
+#[derive(Debug, Clone)]
+pub enum StrategyOutcome {
+    Matched(StrategyMatch),
+    NoMatch,
+    Error(String), // RPC failure, the strategy could not tell
+}
+
+#[derive(Debug, Clone)]
+pub struct StrategyDiagnostic {
+    pub strategy: &'static str,
+    pub outcome: StrategyOutcome,
+}
+
+impl StrategyDiagnostic {
+    // reverts, empty slots and non-matching bytecode are a conclusive "no",
+    // transport failures, node errors and anything unexpected leave the strategy undecided
+    fn new(strategy: &'static str, result: Result<Option<StrategyMatch>>) -> Self {
+        let outcome = match result {
+            Ok(Some(m)) => StrategyOutcome::Matched(m),
+            Ok(None) => StrategyOutcome::NoMatch,
+            Err(e) if is_conclusive_error(&e) => StrategyOutcome::NoMatch,
+            Err(e) => StrategyOutcome::Error(e.to_string()),
+        };
+        StrategyDiagnostic { strategy, outcome }
+    }
+}
+
+// anything not listed here, e.g. a timeout or a malformed node response, is inconclusive
+fn is_conclusive_error(e: &eyre::Report) -> bool {
+    if e.downcast_ref::<Mismatch>().is_some() || e.downcast_ref::<alloy::sol_types::Error>().is_some() {
+        // bytecode that only looks like a proxy, return data of another ABI
+        return true;
+    }
+    match e.downcast_ref::<TransportError>() {
+        Some(RpcError::ErrorResp(payload)) => {
+            payload.code == 3 || payload.message.to_lowercase().contains("revert")
+        }
+        _ => false,
+    }
+}
+
+// the chain data rules the strategy out
+#[derive(Debug)]
+struct Mismatch(&'static str);
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for Mismatch {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectOutcome {
+    Proxy,
+    NotProxy,
+    Inconclusive, // no strategy matched but some of them failed, see the diagnostics
 }
 
 // detection result
 #[derive(Debug, Clone)]
 pub struct DetectResult {
-    pub standard: String,
+    pub outcome: DetectOutcome,
+    pub proxy_type: Option<ProxyType>, // first matching strategy, None if not a proxy
     pub target: Option<Address>,
+    pub matches: Vec<StrategyMatch>, // every matching strategy in detection order
+    pub conflict: bool,              // matching strategies disagree on the target
+    pub admin: Option<Address>,      // EIP-1967 admin slot
+    pub beacon: Option<Address>,     // EIP-1967 beacon slot
+    pub facets: Option<HashMap<String, Address>>, // selector -> facet, only for EIP-2535 diamonds
+    pub diagnostics: Vec<StrategyDiagnostic>,
+}
+
+impl DetectResult {
+    pub fn from_diagnostics(
+        diagnostics: Vec<StrategyDiagnostic>,
+        admin: Option<Address>,
+        beacon: Option<Address>,
+    ) -> Self {
+        let matches: Vec<StrategyMatch> = diagnostics
+            .iter()
+            .filter_map(|d| match &d.outcome {
+                StrategyOutcome::Matched(m) => Some(m.clone()),
+                _ => None,
+            })
+            .collect();
+        let failed = diagnostics
+            .iter()
+            .any(|d| matches!(d.outcome, StrategyOutcome::Error(_)));
+
+        let first = matches.first().cloned();
+        let conflict = matches
+            .iter()
+            .any(|m| Some(m.target) != first.as_ref().map(|f| f.target));
+        let outcome = if first.is_some() {
+            DetectOutcome::Proxy
+        } else if failed {
+            DetectOutcome::Inconclusive
+        } else {
+            DetectOutcome::NotProxy
+        };
+
+        DetectResult {
+            outcome,
+            proxy_type: first.as_ref().map(|f| f.proxy_type),
+            target: first.map(|f| f.target),
+            matches,
+            conflict,
+            admin,
+            beacon,
+            facets: None,
+            diagnostics,
+        }
+    }
+
+    pub fn from_matches(matches: Vec<StrategyMatch>, admin: Option<Address>, beacon: Option<Address>) -> Self {
+        let diagnostics = matches
+            .into_iter()
+            .map(|m| StrategyDiagnostic {
+                strategy: m.proxy_type.as_str(),
+                outcome: StrategyOutcome::Matched(m),
+            })
+            .collect();
+        Self::from_diagnostics(diagnostics, admin, beacon)
+    }
+
+    pub fn diamond(
+        facets: HashMap<String, Address>,
+        admin: Option<Address>,
+        diagnostics: Vec<StrategyDiagnostic>,
+    ) -> Self {
+        DetectResult {
+            outcome: DetectOutcome::Proxy,
+            proxy_type: Some(ProxyType::Eip2535),
+            target: None,
+            matches: vec![],
+            conflict: false,
+            admin,
+            beacon: None,
+            facets: Some(facets),
+            diagnostics,
+        }
+    }
+
+    pub fn is_proxy(&self) -> bool {
+        self.outcome == DetectOutcome::Proxy
+    }
+
+    // inconclusive results came from transient failures and must not be cached
+    pub fn is_conclusive(&self) -> bool {
+        self.outcome != DetectOutcome::Inconclusive
+    }
+}
+
+// apply one FacetCut of a DiamondCut event to the selector -> facet mapping
+fn apply_facet_cut(facets: &mut HashMap<String, Address>, cut: &FacetCut) {
+    for selector in &cut.functionSelectors {
+        let selector_hex = format!("0x{}", hex::encode(selector));
+        match cut.action {
+            0 | 1 => {
+                // add, replace
+                facets.insert(selector_hex, cut.facetAddress);
+            }
+            _ => {
+                // remove
+                facets.remove(&selector_hex);
+            }
+        }
+    }
+}
+
+// detection result valid for every block in [from_block, to_block]
+#[derive(Debug, Clone)]
+pub struct RangeEntry {
+    pub from_block: u64,
+    pub to_block: u64,
+    pub result: DetectResult,
+}
+
+// implementation active in [from_block, to_block], to_block is None if still active
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImplementationPeriod {
+    pub implementation: Address,
+    pub from_block: u64,
+    pub to_block: Option<u64>,
+}
+
+// find the implementation active at the block
+pub fn implementation_at(history: &[ImplementationPeriod], block: u64) -> Option<Address> {
+    history
+        .iter()
+        .find(|p| p.from_block <= block && p.to_block.is_none_or(|to| block <= to))
+        .map(|p| p.implementation)
+}
+
+// append an upgrade to the history, closing the previous period
+fn push_upgrade(history: &mut Vec<ImplementationPeriod>, implementation: Address, block: u64) {
+    if let Some(last) = history.last_mut() {
+        if last.implementation == implementation {
+            return;
+        }
+        if last.from_block >= block {
+            // upgraded again in the same block, the later one wins and the period
+            // before it is open again until the next upgrade
+            history.pop();
+            if let Some(previous) = history.last_mut() {
+                previous.to_block = None;
+            }
+            return push_upgrade(history, implementation, block);
+        }
+        last.to_block = Some(block - 1);
+    }
+    history.push(ImplementationPeriod {
+        implementation,
+        from_block: block,
+        to_block: None,
+    });
 }
 
 // detector
 pub struct ProxyDetector {
     provider: Box<dyn Provider>,
+    chain: config::ChainConfig,
+    chain_id: u64,
+    cache_ttl: Duration,
 }
 
 impl ProxyDetector {
     pub async fn new(chain_config: &config::ChainConfig) -> Result<Self> {
-        let provider = new_rpc_client(chain_config).await?;
-        Ok(Self { provider })
+        // a single misbehaving node can't make up an implementation address
+        let provider = new_quorum_rpc_client(chain_config).await?;
+        Ok(Self {
+            provider,
+            chain: chain_config.clone(),
+            chain_id: chain_config.id,
+            cache_ttl: Duration::from_secs(
+                chain_config.proxy_cache_ttl.unwrap_or(DEFAULT_PROXY_CACHE_TTL),
+            ),
+        })
     }
 
+    #[instrument(name = "detect_proxy", skip(self), fields(chain_id = self.chain_id))]
     pub async fn detect_proxy_target(&self, proxy_address: &str) -> Result<DetectResult> {
         let address = proxy_address
             .parse::<Address>()
             .map_err(|e| eyre!("Invalid proxy address {}: {}", proxy_address, e))?;
 
-        // Check cache first
-        let mut cache = CACHE.lock().unwrap();
-        if let Some(result) = cache.get(proxy_address) {
-            return Ok(result.clone());
+        // concurrent lookups of the same proxy share one detection
+        let detect_result = CACHE
+            .get_or_insert_with(
+                CacheKey::new(self.chain_id, address),
+                self.cache_ttl,
+                DetectResult::is_conclusive,
+                || self._detect(address, BlockId::latest()),
+            )
+            .await;
+        Ok(detect_result)
+    }
+
+    // run all the detection strategies against the state at a historical block
+    pub async fn detect_proxy_target_at(&self, proxy_address: &str, block: u64) -> Result<DetectResult> {
+        let address = proxy_address
+            .parse::<Address>()
+            .map_err(|e| eyre!("Invalid proxy address {}: {}", proxy_address, e))?;
+
+        let key = CacheKey::new(self.chain_id, address);
+        let cached = Self::_cached_at(&key, block);
+        metrics::observe_cache_lookup("proxy_history", cached.is_some());
+        if let Some(result) = cached {
+            return Ok(result);
+        }
+
+        let detect_result = self._detect(address, BlockId::number(block)).await;
+        if detect_result.is_conclusive() {
+            let to_block = self._valid_until(address, block, &detect_result).await;
+            Self::_cache_range(key, block, to_block, &detect_result);
         }
+        Ok(detect_result)
+    }
 
+    // last block the detection at the block is known to hold for: up to the next logged
+    // upgrade, or the latest block for a clone. the block itself when that is unknown.
+    async fn _valid_until(&self, proxy: Address, block: u64, result: &DetectResult) -> u64 {
+        let Some(proxy_type) = result.proxy_type.filter(|_| result.is_proxy()) else {
+            return block;
+        };
+        let Ok(latest) = self.provider.get_block_number().await else {
+            return block;
+        };
+        let filters = match proxy_type.upgrade_tracking() {
+            UpgradeTracking::Immutable => return latest.max(block),
+            UpgradeTracking::Event(topic, true) => match (proxy_type, result.beacon) {
+                // the beacon upgrades, or the proxy switches to another beacon
+                (ProxyType::Eip1967Beacon, Some(beacon)) => vec![
+                    Filter::new().address(beacon).event_signature(topic),
+                    Filter::new().address(proxy).event_signature(BeaconUpgraded::SIGNATURE_HASH),
+                ],
+                (ProxyType::Eip1967Beacon, None) => return block,
+                _ => vec![Filter::new().address(proxy).event_signature(topic)],
+            },
+            _ => return block,
+        };
 
+        let scan_to = latest.min(block + MAX_PERIOD_SCAN_BLOCKS);
+        let mut valid_until = scan_to.max(block);
+        for filter in filters {
+            match get_logs_in_chunks(self.provider.as_ref(), filter, block + 1, scan_to).await {
+                Ok(logs) => {
+                    if let Some(upgraded) = logs.iter().filter_map(|log| log.block_number).min() {
+                        valid_until = valid_until.min(upgraded - 1);
+                    }
+                }
+                Err(_) => return block,
+            }
+        }
+        valid_until
+    }
+
+    async fn _detect(&self, address: Address, block: BlockId) -> DetectResult {
         // 使用 join! 宏并行执行 futures
         let (
             eip_1167,
+            bytecode_pattern,
             eip_1967_beacon,
             open_zeppelin,
             eip_1822,
@@ -93,47 +535,61 @@ impl ProxyDetector {
             eip_1967_logic,
             gnosis_safe,
             compound,
+            admin,
+            beacon,
         ) = join!(
             async {
                 (
-                    "eip_1167",
-                    self._get_code_and_parse_1167_minimal(address).await,
+                    ProxyType::Eip1167,
+                    self._get_code_and_parse_1167_minimal(address, block).await,
                 )
             },
-            async { ("eip_1967_beacon", self._get_eip_1967_beacon(address).await) },
+            self._get_code_and_match_patterns(address, block),
+            async { (ProxyType::Eip1967Beacon, self._get_eip_1967_beacon(address, block).await) },
             async {
                 (
-                    "open_zeppelin",
-                    self._get_open_zeppelin_implementation(address).await,
+                    ProxyType::OpenZeppelin,
+                    self._get_open_zeppelin_implementation(address, block).await,
                 )
             },
-            async { ("eip_1822", self._get_eip_1822_logic(address).await) },
+            async { (ProxyType::Eip1822, self._get_eip_1822_logic(address, block).await) },
             async {
                 (
-                    "eip_897",
-                    self._request_from_eth_call(address, EIP_897_METHOD[0].to_string())
+                    ProxyType::Eip897,
+                    self._request_from_eth_call(address, EIP_897_METHOD[0].to_string(), block)
                         .await,
                 )
             },
-            async { ("eip_1967_logic", self._get_eip_1967_logic(address).await) },
+            async { (ProxyType::Eip1967Logic, self._get_eip_1967_logic(address, block).await) },
             async {
                 (
-                    "gnosis_safe",
-                    self._request_from_eth_call(address, GNOSIS_SAFE_PROXY_METHOD[0].to_string())
+                    ProxyType::GnosisSafe,
+                    self._request_from_eth_call(address, GNOSIS_SAFE_PROXY_METHOD[0].to_string(), block)
                         .await,
                 )
             },
             async {
                 (
-                    "compound",
-                    self._request_from_eth_call(address, COMPTROLLER_PROXY_METHOD[0].to_string())
+                    ProxyType::Compound,
+                    self._request_from_eth_call(address, COMPTROLLER_PROXY_METHOD[0].to_string(), block)
                         .await,
                 )
-            }
+            },
+            self._read_storage_slot(address, EIP_1967_ADMIN_SLOT, block),
+            self._read_storage_slot(address, EIP_1967_BEACON_SLOT, block)
         );
 
-        let results = vec![
-            eip_1167,
+        let strategy = |(proxy_type, result): (ProxyType, Result<Option<Address>>)| {
+            let result = result.map(|t| t.map(|target| StrategyMatch { proxy_type, target }));
+            StrategyDiagnostic::new(proxy_type.as_str(), result)
+        };
+        let pattern = bytecode_pattern.map(|t| t.map(|(proxy_type, target)| StrategyMatch { proxy_type, target }));
+
+        let mut diagnostics = vec![
+            strategy(eip_1167),
+            StrategyDiagnostic::new("bytecode_pattern", pattern),
+        ];
+        diagnostics.extend([
             eip_1967_beacon,
             open_zeppelin,
             eip_1822,
@@ -141,33 +597,312 @@ impl ProxyDetector {
             eip_1967_logic,
             gnosis_safe,
             compound,
-        ];
+        ]
+        .into_iter()
+        .map(strategy));
+        let admin = admin.ok().flatten();
+        let beacon = beacon.ok().flatten();
 
-        for (key, result) in results {
-            if let Ok(Some(target)) = result {
-                let detect_reuslt = DetectResult {
-                    standard: key.to_string(),
-                    target: Some(target),
-                };
-                cache.insert(proxy_address.to_string(), detect_reuslt.clone());
-                return Ok(detect_reuslt);
+        let matched = diagnostics
+            .iter()
+            .any(|d| matches!(d.outcome, StrategyOutcome::Matched(_)));
+        if matched {
+            return DetectResult::from_diagnostics(diagnostics, admin, beacon);
+        }
+
+        // a diamond has no single target, every selector is routed to its own facet
+        match self._get_diamond_facets(address, block).await {
+            Ok(Some(facets)) => return DetectResult::diamond(facets, admin, diagnostics),
+            Ok(None) => diagnostics.push(StrategyDiagnostic::new(ProxyType::Eip2535.as_str(), Ok(None))),
+            Err(e) => diagnostics.push(StrategyDiagnostic::new(ProxyType::Eip2535.as_str(), Err(e))),
+        }
+
+        DetectResult::from_diagnostics(diagnostics, admin, beacon)
+    }
+
+    // facet that handles the selector of a diamond, by the facetAddress(bytes4) loupe call
+    pub async fn facet_address(&self, diamond: Address, selector: &str, block: BlockId) -> Result<Option<Address>> {
+        let selector = selector.parse::<FixedBytes<4>>()?;
+        let data = facetAddressCall { _functionSelector: selector }.abi_encode();
+        let response = self._eth_call(diamond, data.into(), block).await?;
+        let facet = facetAddressCall::abi_decode_returns(&response, true)?.facetAddress_;
+        if facet == Address::ZERO {
+            return Ok(None);
+        }
+        Ok(Some(facet))
+    }
+
+    // selector -> facet mapping of a diamond rebuilt from the DiamondCut events in [from_block, to_block]
+    pub async fn diamond_cut_facets(
+        &self,
+        diamond: Address,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<HashMap<String, Address>> {
+        let filter = Filter::new()
+            .address(diamond)
+            .event_signature(DiamondCut::SIGNATURE_HASH);
+        let mut logs = get_logs_in_chunks(self.provider.as_ref(), filter, from_block, to_block).await?;
+        logs.sort_by_key(|log| (log.block_number, log.log_index));
+
+        let mut facets = HashMap::new();
+        for log in logs {
+            let event = DiamondCut::decode_log_data(log.data(), true)?;
+            for cut in &event._diamondCut {
+                apply_facet_cut(&mut facets, cut);
             }
         }
-        let detect_result= DetectResult {
-            standard: "".to_string(),
-            target: None,
+        Ok(facets)
+    }
+
+    // selector -> facet mapping of an EIP-2535 diamond by the facets() loupe call.
+    // diamonds too large for facets() that declare the diamond loupe with ERC-165 replay
+    // their DiamondCut events since the deployment, when the explorer knows the deployment
+    // and it is at most MAX_DIAMOND_CUT_SCAN_BLOCKS old, or are read facet by facet.
+    async fn _get_diamond_facets(&self, proxy: Address, block: BlockId) -> Result<Option<HashMap<String, Address>>> {
+        let data = facetsCall {}.abi_encode();
+        match self._eth_call(proxy, data.into(), block).await {
+            Ok(response) => {
+                // return data of another ABI is no loupe
+                if let Ok(decoded) = facetsCall::abi_decode_returns(&response, true) {
+                    let mut facets = HashMap::new();
+                    for facet in decoded.facets_ {
+                        for selector in facet.functionSelectors {
+                            facets.insert(format!("0x{}", hex::encode(selector)), facet.facetAddress);
+                        }
+                    }
+                    return Ok(if facets.is_empty() { None } else { Some(facets) });
+                }
+            }
+            Err(e) if is_conclusive_error(&e) => {}
+            Err(e) => return Err(e),
+        }
+
+        // any contract may answer a loupe call, only a declared loupe is trusted
+        let data = supportsInterfaceCall {
+            interfaceId: FixedBytes(DIAMOND_LOUPE_INTERFACE_ID),
+        }
+        .abi_encode();
+        let supported = match self._eth_call(proxy, data.into(), block).await {
+            Ok(response) => supportsInterfaceCall::abi_decode_returns(&response, true).is_ok_and(|r| r._0),
+            Err(e) if is_conclusive_error(&e) => false,
+            Err(e) => return Err(e),
         };
+        if !supported {
+            return Ok(None);
+        }
 
-        cache.insert(proxy_address.to_string(), detect_result.clone());
-        Ok(detect_result)
+        if let Some(deployed) = self.deployment_block(proxy).await {
+            let to_block = match block {
+                BlockId::Number(alloy::eips::BlockNumberOrTag::Number(number)) => number,
+                _ => self.provider.get_block_number().await?,
+            };
+            if to_block.saturating_sub(deployed) <= MAX_DIAMOND_CUT_SCAN_BLOCKS {
+                let facets = self.diamond_cut_facets(proxy, deployed, to_block).await?;
+                if !facets.is_empty() {
+                    return Ok(Some(facets));
+                }
+            }
+        }
+
+        let facets = self._loupe_facets(proxy, block).await?;
+        Ok(if facets.is_empty() { None } else { Some(facets) })
+    }
+
+    // selector -> facet mapping by facetAddresses() and facetFunctionSelectors(address) per facet
+    async fn _loupe_facets(&self, diamond: Address, block: BlockId) -> Result<HashMap<String, Address>> {
+        let data = facetAddressesCall {}.abi_encode();
+        let response = self._eth_call(diamond, data.into(), block).await?;
+        let addresses = facetAddressesCall::abi_decode_returns(&response, true)?.facetAddresses_;
+
+        let mut facets = HashMap::new();
+        for facet in addresses {
+            let data = facetFunctionSelectorsCall { _facet: facet }.abi_encode();
+            let response = self._eth_call(diamond, data.into(), block).await?;
+            let selectors = facetFunctionSelectorsCall::abi_decode_returns(&response, true)?.facetFunctionSelectors_;
+            for selector in selectors {
+                facets.insert(format!("0x{}", hex::encode(selector)), facet);
+            }
+        }
+        Ok(facets)
+    }
+
+    // block of the contract creation transaction, None when the chain has no explorer API
+    // or the explorer does not know the contract
+    pub async fn deployment_block(&self, address: Address) -> Option<u64> {
+        if !self.chain.has_explorer_api() {
+            return None;
+        }
+        let scan = new_scan_client(&self.chain).ok()?;
+        let creation = traced_explorer_call(&self.chain, "getcontractcreation", scan.contract_creation_data(address))
+            .await
+            .ok()?;
+        let tx = self
+            .provider
+            .get_transaction_by_hash(creation.transaction_hash)
+            .await
+            .ok()??;
+        tx.block_number
+    }
+
+    // look up the historical cache for a range containing the block
+    fn _cached_at(key: &CacheKey, block: u64) -> Option<DetectResult> {
+        let mut cache = HISTORY_CACHE.lock().unwrap();
+        cache
+            .cache_get(key)?
+            .iter()
+            .find(|entry| entry.from_block <= block && block <= entry.to_block)
+            .map(|entry| entry.result.clone())
+    }
+
+    // remember the detection result for every block in [from_block, to_block]
+    fn _cache_range(key: CacheKey, from_block: u64, to_block: u64, result: &DetectResult) {
+        let mut cache = HISTORY_CACHE.lock().unwrap();
+        let entries = cache.cache_get_or_set_with(key, Vec::new);
+        entries.retain(|entry| entry.to_block < from_block || to_block < entry.from_block);
+        entries.push(RangeEntry {
+            from_block,
+            to_block,
+            result: result.clone(),
+        });
+    }
+
+    // cached detection result at the latest block
+    pub fn cached(key: &CacheKey) -> Option<DetectResult> {
+        CACHE.get(key)
+    }
+
+    // cached detection results of historical block ranges
+    pub fn cached_history(key: &CacheKey) -> Vec<RangeEntry> {
+        let mut cache = HISTORY_CACHE.lock().unwrap();
+        cache.cache_get(key).cloned().unwrap_or_default()
+    }
+
+    // drop every cached detection of the proxy
+    pub fn invalidate(key: &CacheKey) {
+        CACHE.remove(key);
+        HISTORY_CACHE.lock().unwrap().cache_remove(key);
+    }
+
+    pub fn clear_cache() {
+        CACHE.clear();
+        HISTORY_CACHE.lock().unwrap().cache_clear();
+    }
+
+    // number of cached (latest, historical) entries
+    pub fn cache_len() -> (usize, usize) {
+        (CACHE.len(), HISTORY_CACHE.lock().unwrap().cache_size())
+    }
+
+    // implementation history of the proxy in [from_block, to_block], built from the
+    // implementation at from_block and the upgrade events of its proxy type in the window.
+    // without an archive node the implementation before the first upgrade in the window is
+    // unknown. only periods every upgrade is logged for are cached as exact.
+    // empty if the address is not an upgradeable proxy.
+    pub async fn implementation_history(
+        &self,
+        proxy: Address,
+        from_block: u64,
+        to_block: u64,
+    ) -> Result<Vec<ImplementationPeriod>> {
+        let mut history = vec![];
+
+        // most sampled contracts are not proxies, the cached latest detection spares them
+        // the historical detection and the log scan
+        let latest = self.detect_proxy_target(&format!("{:#x}", proxy)).await?;
+        if latest.outcome == DetectOutcome::NotProxy {
+            return Ok(history);
+        }
+
+        // implementation active when the window starts
+        let mut initial = None;
+        if self.chain.archive {
+            let detected = self
+                .detect_proxy_target_at(&format!("{:#x}", proxy), from_block)
+                .await?;
+            if detected.is_proxy() {
+                initial = Some(detected);
+            }
+        }
+        let detected = initial.as_ref().unwrap_or(&latest);
+        let Some(proxy_type) = detected.proxy_type else {
+            return Ok(history);
+        };
+
+        let exact = match proxy_type.upgrade_tracking() {
+            UpgradeTracking::Immutable => {
+                if let Some(target) = detected.target {
+                    push_upgrade(&mut history, target, from_block);
+                }
+                true
+            }
+            UpgradeTracking::Event(topic, every_upgrade) => {
+                if let Some(target) = initial.as_ref().and_then(|initial| initial.target) {
+                    push_upgrade(&mut history, target, from_block);
+                }
+                let emitter = match proxy_type {
+                    ProxyType::Eip1967Beacon => detected.beacon.ok_or_eyre("beacon proxy without a beacon")?,
+                    _ => proxy,
+                };
+                let filter = Filter::new().address(emitter).event_signature(topic);
+                let mut logs = get_logs_in_chunks(self.provider.as_ref(), filter, from_block, to_block).await?;
+                logs.sort_by_key(|log| (log.block_number, log.log_index));
+                for log in logs {
+                    let (Some(implementation), Some(block)) = (upgraded_implementation(&log), log.block_number) else {
+                        continue;
+                    };
+                    push_upgrade(&mut history, implementation, block.max(from_block));
+                }
+
+                // a beacon proxy pointed at another beacon follows the Upgraded events of that one
+                let beacon_switched = proxy_type == ProxyType::Eip1967Beacon && {
+                    let filter = Filter::new().address(proxy).event_signature(BeaconUpgraded::SIGNATURE_HASH);
+                    !get_logs_in_chunks(self.provider.as_ref(), filter, from_block, to_block).await?.is_empty()
+                };
+                every_upgrade && !beacon_switched
+            }
+            UpgradeTracking::Untracked => {
+                if let Some(target) = initial.as_ref().and_then(|initial| initial.target) {
+                    push_upgrade(&mut history, target, from_block);
+                }
+                false
+            }
+        };
+
+        // periods with every upgrade logged are known exactly, cache them for the historical lookups
+        if exact {
+            for period in &history {
+                let matches = vec![StrategyMatch {
+                    proxy_type,
+                    target: period.implementation,
+                }];
+                let result = DetectResult::from_matches(matches, detected.admin, detected.beacon);
+                Self::_cache_range(CacheKey::new(self.chain_id, proxy), period.from_block, period.to_block.unwrap_or(to_block), &result);
+            }
+        }
+
+        Ok(history)
     }
 
     // read address from the storage slot
-    async fn _read_storage_slot(&self, proxy: Address, slot: &str) -> Result<Option<Address>> {
+    async fn _read_storage_slot(&self, proxy: Address, slot: &str, block: BlockId) -> Result<Option<Address>> {
         let slot = slot.parse::<U256>()?;
-        let storage = self.provider.get_storage_at(proxy.clone(), slot).await?;
-        let addr = Self::_read_address(&B256::from(storage))?;
-        Ok(Some(addr))
+        let storage = self
+            .provider
+            .get_storage_at(proxy, slot)
+            .block_id(block)
+            .await?;
+        // an empty slot means the strategy does not apply
+        Ok(Self::_read_address(&B256::from(storage)).ok())
+    }
+
+    // raw eth_call against the state at the block
+    async fn _eth_call(&self, to: Address, data: Bytes, block: BlockId) -> Result<Bytes> {
+        let call = TransactionRequest::default()
+            .to(to)
+            .input(TransactionInput::new(data));
+        let response = self.provider.call(&call).block(block).await?;
+        Ok(response)
     }
 
     // request address by eth_call
@@ -175,14 +910,13 @@ impl ProxyDetector {
         &self,
         proxy: Address,
         data: String,
+        block: BlockId,
     ) -> Result<Option<Address>> {
-        let call = TransactionRequest::default()
-            .to(proxy.clone())
-            .input(TransactionInput::new(data.parse::<Bytes>()?));
-        let response = self.provider.call(&call).await?;
+        let response = self._eth_call(proxy, data.parse::<Bytes>()?, block).await?;
 
         if response.0.len() < 32 {
-            return Err(eyre!("Response is less than 32 bytes"));
+            // not an address returning method
+            return Ok(None);
         }
 
         // ensure the response is 32 bytes long for B256
@@ -190,73 +924,105 @@ impl ProxyDetector {
             .try_into()
             .map_err(|_| eyre!("not 32 bytes"))?;
 
-        let address = Self::_read_address(&B256::new(response_bytes))?;
-        Ok(Some(address))
+        Ok(Self::_read_address(&B256::new(response_bytes)).ok())
     }
 
-    async fn _get_code_and_parse_1167_minimal(&self, proxy: Address) -> Result<Option<Address>> {
-        let bytecode = self.provider.get_code_at(proxy.clone()).await?.0;
+    async fn _get_code_and_parse_1167_minimal(&self, proxy: Address, block: BlockId) -> Result<Option<Address>> {
+        let bytecode = self.provider.get_code_at(proxy).block_id(block).await?.0;
         let bytecode_str = hex::encode(bytecode);
 
-        if !bytecode_str.starts_with(&EIP_1167_BYTECODE_PREFIX.trim_start_matches("0x")) {
-            return Err(eyre!("Not an EIP-1167 bytecode"));
+        if !bytecode_str.starts_with(EIP_1167_BYTECODE_PREFIX.trim_start_matches("0x")) {
+            return Ok(None);
         }
 
         let prefix_len = EIP_1167_BYTECODE_PREFIX.len() - 2; // remove "0x"
+        if bytecode_str.len() < prefix_len + 2 {
+            return Err(Mismatch("Malformed EIP-1167 bytecode").into());
+        }
         let push_n_hex = &bytecode_str[prefix_len..prefix_len + 2];
-        let address_length = usize::from_str_radix(push_n_hex, 16)? - 0x5f;
+        let address_length = usize::from_str_radix(push_n_hex, 16)
+            .map_err(|_| Mismatch("Malformed EIP-1167 bytecode"))?
+            .saturating_sub(0x5f);
 
-        if address_length < 1 || address_length > 20 {
-            return Err(eyre!("Invalid address length in EIP-1167 bytecode"));
+        if !(1..=20).contains(&address_length) {
+            return Err(Mismatch("Invalid address length in EIP-1167 bytecode").into());
         }
 
         let address_start = prefix_len + 2;
         let address_end = address_start + address_length * 2;
+        if bytecode_str.len() < address_end + 22 {
+            return Err(Mismatch("Malformed EIP-1167 bytecode").into());
+        }
         let address_hex = &bytecode_str[address_start..address_end];
         let suffix = &bytecode_str[address_end + 22..];
 
-        if !suffix.starts_with(&EIP_1167_BYTECODE_SUFFIX.trim_start_matches("0x")) {
-            return Err(eyre!("Invalid EIP-1167 bytecode suffix"));
+        if !suffix.starts_with(EIP_1167_BYTECODE_SUFFIX.trim_start_matches("0x")) {
+            return Err(Mismatch("Invalid EIP-1167 bytecode suffix").into());
         }
 
-        let addr = Address::from_str(&format!("0x{}", address_hex.trim_start_matches('0')))?;
+        let addr = Address::from_str(&format!("0x{}", address_hex.trim_start_matches('0')))
+            .map_err(|_| Mismatch("Invalid address in EIP-1167 bytecode"))?;
         Ok(Some(addr))
     }
 
+    // clones, MetaProxy and slot-based minimal proxies recognised by their bytecode
+    async fn _get_code_and_match_patterns(
+        &self,
+        proxy: Address,
+        block: BlockId,
+    ) -> Result<Option<(ProxyType, Address)>> {
+        let bytecode = self.provider.get_code_at(proxy).block_id(block).await?;
+
+        if let Some(found) = bytecode::parse_clone_bytecode(&bytecode) {
+            return Ok(Some(found));
+        }
+        if bytecode::is_erc1967_minimal_proxy(&bytecode) {
+            let target = self._read_storage_slot(proxy, EIP_1967_LOGIC_SLOT, block).await?;
+            return Ok(target.map(|addr| (ProxyType::Erc1967Minimal, addr)));
+        }
+        if bytecode::is_safe_proxy(&bytecode) {
+            let target = self._read_storage_slot(proxy, GNOSIS_SAFE_MASTER_COPY_SLOT, block).await?;
+            return Ok(target.map(|addr| (ProxyType::GnosisSafeSlot, addr)));
+        }
+        Ok(None)
+    }
+
     // get logic address for EIP-1967
-    async fn _get_eip_1967_logic(&self, proxy: Address) -> Result<Option<Address>> {
-        self._read_storage_slot(proxy, EIP_1967_LOGIC_SLOT).await
+    async fn _get_eip_1967_logic(&self, proxy: Address, block: BlockId) -> Result<Option<Address>> {
+        self._read_storage_slot(proxy, EIP_1967_LOGIC_SLOT, block).await
     }
 
     // beacon address for EIP-1967
-    async fn _get_eip_1967_beacon(&self, proxy: Address) -> Result<Option<Address>> {
-        let beacon = self._read_storage_slot(proxy, EIP_1967_BEACON_SLOT).await?;
+    async fn _get_eip_1967_beacon(&self, proxy: Address, block: BlockId) -> Result<Option<Address>> {
+        let beacon = self._read_storage_slot(proxy, EIP_1967_BEACON_SLOT, block).await?;
         if let Some(beacon_addr) = beacon {
-            if let Ok(target) = self
-                ._request_from_eth_call(beacon_addr, EIP_1167_BEACON_METHODS[0].to_string())
-                .await
-            {
-                return Ok(target);
+            let mut last_error = None;
+            for method in EIP_1167_BEACON_METHODS {
+                match self
+                    ._request_from_eth_call(beacon_addr, method.to_string(), block)
+                    .await
+                {
+                    Ok(Some(target)) => return Ok(Some(target)),
+                    Ok(None) => {}
+                    Err(e) => last_error = Some(e),
+                }
             }
-            if let Ok(target) = self
-                ._request_from_eth_call(beacon_addr, EIP_1167_BEACON_METHODS[1].to_string())
-                .await
-            {
-                return Ok(target);
+            if let Some(e) = last_error {
+                return Err(e);
             }
         }
         Ok(None)
     }
 
     // OpenZeppelin implementation address
-    async fn _get_open_zeppelin_implementation(&self, proxy: Address) -> Result<Option<Address>> {
-        self._read_storage_slot(proxy, OPEN_ZEPPELIN_IMPLEMENTATION_SLOT)
+    async fn _get_open_zeppelin_implementation(&self, proxy: Address, block: BlockId) -> Result<Option<Address>> {
+        self._read_storage_slot(proxy, OPEN_ZEPPELIN_IMPLEMENTATION_SLOT, block)
             .await
     }
 
     // EIP-1822 logic adddress
-    async fn _get_eip_1822_logic(&self, proxy: Address) -> Result<Option<Address>> {
-        self._read_storage_slot(proxy, EIP_1822_LOGIC_SLOT).await
+    async fn _get_eip_1822_logic(&self, proxy: Address, block: BlockId) -> Result<Option<Address>> {
+        self._read_storage_slot(proxy, EIP_1822_LOGIC_SLOT, block).await
     }
 
     // read and validate the address
@@ -277,11 +1043,13 @@ mod tests {
     use std::str::FromStr;
 
     use super::*;
+    use crate::pkg::mock::anvil::AnvilHarness;
+    use crate::pkg::mock::server::MockServer;
     use crate::CONFIG;
 
     #[tokio::test]
     async fn test_proxy() {
-        let chain_config = CONFIG.chains.get(0).expect("chain config not found");
+        let chain_config = CONFIG.chains.first().expect("chain config not found");
 
         let detector = match ProxyDetector::new(chain_config).await {
             Ok(d) => d,
@@ -290,7 +1058,7 @@ mod tests {
 
         let non_proxy= "0xd4e96ef8eee8678dbff4d535e033ed1a4f7605b7";
         let result = match detector
-            .detect_proxy_target(&non_proxy)
+            .detect_proxy_target(non_proxy)
             .await
         {
             Ok(r) => r,
@@ -300,7 +1068,7 @@ mod tests {
 
         let eip_1967 = "0x4aa799c5dfc01ee7d790e3bf1a7c2257ce1dceff";
         let result = match detector
-            .detect_proxy_target(&eip_1967)
+            .detect_proxy_target(eip_1967)
             .await
         {
             Ok(r) => r,
@@ -308,13 +1076,23 @@ mod tests {
         };
         println!("{} {:?}", eip_1967, result);
 
+        let historical = match detector
+            .detect_proxy_target_at(eip_1967, 18_000_000)
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => panic!("{}", e),
+        };
+        println!("{} at 18000000 {:?}", eip_1967, historical);
+
 
         let eip_897 = "0x8260b9eC6d472a34AD081297794d7Cc00181360a";
         let specific_result = match detector
             ._request_from_eth_call(
-                Address::from_str(&eip_897)
+                Address::from_str(eip_897)
                     .expect("wrong address"),
                 EIP_897_METHOD[0].to_string(),
+                BlockId::latest(),
             )
             .await
         {
@@ -323,4 +1101,371 @@ mod tests {
         };
         println!("{} {:?}", eip_897, specific_result);
     }
+
+    #[test]
+    fn test_implementation_history() {
+        let a = Address::with_last_byte(0xa);
+        let b = Address::with_last_byte(0xb);
+        let c = Address::with_last_byte(0xc);
+
+        let mut history = vec![];
+        push_upgrade(&mut history, a, 100);
+        push_upgrade(&mut history, a, 150); // no-op upgrade to the same implementation
+        push_upgrade(&mut history, b, 200);
+        push_upgrade(&mut history, c, 200); // upgraded twice in one block
+        assert_eq!(
+            history,
+            vec![
+                ImplementationPeriod { implementation: a, from_block: 100, to_block: Some(199) },
+                ImplementationPeriod { implementation: c, from_block: 200, to_block: None },
+            ]
+        );
+
+        assert_eq!(implementation_at(&history, 99), None);
+        assert_eq!(implementation_at(&history, 100), Some(a));
+        assert_eq!(implementation_at(&history, 199), Some(a));
+        assert_eq!(implementation_at(&history, 200), Some(c));
+        assert_eq!(implementation_at(&history, 1_000_000), Some(c));
+
+        // upgraded away and back in one block, the earlier period goes on
+        let mut history = vec![];
+        push_upgrade(&mut history, a, 100);
+        push_upgrade(&mut history, b, 200);
+        push_upgrade(&mut history, a, 200);
+        assert_eq!(
+            history,
+            vec![ImplementationPeriod { implementation: a, from_block: 100, to_block: None }]
+        );
+        assert_eq!(implementation_at(&history, 1_000_000), Some(a));
+        push_upgrade(&mut history, c, 300);
+        assert_eq!(implementation_at(&history, 299), Some(a));
+        assert_eq!(implementation_at(&history, 300), Some(c));
+    }
+
+    #[tokio::test]
+    async fn test_implementation_history_offline() {
+        let server = MockServer::start(1, "proxy_strategies").await.expect("mock server not started");
+        let detector = ProxyDetector::new(&server.chain()).await.expect("detector not created");
+        // from block 101, test_strategies_offline caches the detections up to block 100
+
+        // a clone never changes its implementation, even without an archive node
+        let clone = detector.implementation_history(fixture_address("a1", 1), 101, 200).await.unwrap();
+        assert_eq!(
+            clone,
+            vec![ImplementationPeriod { implementation: fixture_address("b1", 1), from_block: 101, to_block: None }]
+        );
+
+        // the beacon logs the upgrade of a beacon proxy, the implementation before it needs an archive node
+        let beacon = detector.implementation_history(fixture_address("a1", 3), 101, 200).await.unwrap();
+        assert_eq!(
+            beacon,
+            vec![ImplementationPeriod { implementation: fixture_address("b2", 3), from_block: 150, to_block: None }]
+        );
+        let cached = ProxyDetector::cached_history(&CacheKey::new(1, fixture_address("a1", 3)));
+        assert!(cached.iter().any(|entry| entry.from_block == 150 && entry.to_block == 200));
+
+        // Compound logs NewImplementation(old, new)
+        let compound = detector.implementation_history(fixture_address("a1", 9), 101, 200).await.unwrap();
+        assert_eq!(
+            compound,
+            vec![ImplementationPeriod { implementation: fixture_address("b2", 9), from_block: 160, to_block: None }]
+        );
+
+        // nothing tracks an EIP-1822 upgrade, no history without the state at the block
+        let eip_1822 = detector.implementation_history(fixture_address("a1", 5), 101, 200).await.unwrap();
+        assert!(eip_1822.is_empty());
+        assert!(ProxyDetector::cached_history(&CacheKey::new(1, fixture_address("a1", 5))).is_empty());
+    }
+
+    #[test]
+    fn test_history_cache_ranges() {
+        let proxy = Address::with_last_byte(0x30);
+        let key = CacheKey::new(1, proxy);
+        let result = |byte: u8| {
+            let matches = vec![StrategyMatch {
+                proxy_type: ProxyType::Eip1967Logic,
+                target: Address::with_last_byte(byte),
+            }];
+            DetectResult::from_matches(matches, None, None)
+        };
+
+        ProxyDetector::_cache_range(key, 100, 199, &result(0xa));
+        ProxyDetector::_cache_range(key, 200, 300, &result(0xb));
+        assert!(ProxyDetector::_cached_at(&key, 99).is_none());
+        assert_eq!(ProxyDetector::_cached_at(&key, 150).unwrap().target, result(0xa).target);
+        assert_eq!(ProxyDetector::_cached_at(&key, 200).unwrap().target, result(0xb).target);
+
+        // an overlapping range replaces the stale entries
+        ProxyDetector::_cache_range(key, 150, 250, &result(0xc));
+        assert!(ProxyDetector::_cached_at(&key, 120).is_none());
+        assert_eq!(ProxyDetector::_cached_at(&key, 250).unwrap().target, result(0xc).target);
+        assert!(ProxyDetector::_cached_at(&key, 300).is_none());
+
+        // the same address on another chain does not share the entries
+        assert!(ProxyDetector::_cached_at(&CacheKey::new(42161, proxy), 250).is_none());
+    }
+
+    #[test]
+    fn test_apply_facet_cut() {
+        let facet_a = Address::with_last_byte(0xa);
+        let facet_b = Address::with_last_byte(0xb);
+        let cut = |facet: Address, action: u8, selectors: &[[u8; 4]]| FacetCut {
+            facetAddress: facet,
+            action,
+            functionSelectors: selectors.iter().map(|s| FixedBytes::from(*s)).collect(),
+        };
+
+        let mut facets = HashMap::new();
+        apply_facet_cut(&mut facets, &cut(facet_a, 0, &[[0xa9, 0x05, 0x9c, 0xbb], [0x09, 0x5e, 0xa7, 0xb3]]));
+        apply_facet_cut(&mut facets, &cut(facet_b, 1, &[[0x09, 0x5e, 0xa7, 0xb3]]));
+        assert_eq!(facets.get("0xa9059cbb"), Some(&facet_a));
+        assert_eq!(facets.get("0x095ea7b3"), Some(&facet_b));
+
+        apply_facet_cut(&mut facets, &cut(Address::ZERO, 2, &[[0xa9, 0x05, 0x9c, 0xbb]]));
+        assert_eq!(facets.get("0xa9059cbb"), None);
+        assert_eq!(facets.len(), 1);
+    }
+
+    #[test]
+    fn test_detect_result_conflict() {
+        let a = Address::with_last_byte(0xa);
+        let b = Address::with_last_byte(0xb);
+        let admin = Some(Address::with_last_byte(0xad));
+
+        let agreed = DetectResult::from_matches(
+            vec![
+                StrategyMatch { proxy_type: ProxyType::Eip897, target: a },
+                StrategyMatch { proxy_type: ProxyType::Eip1967Logic, target: a },
+            ],
+            admin,
+            None,
+        );
+        assert_eq!(agreed.proxy_type, Some(ProxyType::Eip897));
+        assert_eq!(agreed.target, Some(a));
+        assert_eq!(agreed.matches.len(), 2);
+        assert!(!agreed.conflict);
+        assert_eq!(agreed.admin, admin);
+
+        let conflicting = DetectResult::from_matches(
+            vec![
+                StrategyMatch { proxy_type: ProxyType::Eip897, target: a },
+                StrategyMatch { proxy_type: ProxyType::Eip1967Logic, target: b },
+            ],
+            None,
+            None,
+        );
+        assert!(conflicting.conflict);
+
+        let not_proxy = DetectResult::from_matches(vec![], None, None);
+        assert_eq!(not_proxy.outcome, DetectOutcome::NotProxy);
+        assert!(!not_proxy.is_proxy());
+        assert_eq!(not_proxy.target, None);
+        assert!(!not_proxy.conflict);
+    }
+
+    #[test]
+    fn test_detect_outcome() {
+        let target = Address::with_last_byte(0xa);
+
+        // a failed strategy without any match leaves the result undecided
+        let diagnostics = vec![
+            StrategyDiagnostic::new("eip_1167", Ok(None)),
+            StrategyDiagnostic {
+                strategy: "eip_897",
+                outcome: StrategyOutcome::Error("request timed out".to_string()),
+            },
+        ];
+        let result = DetectResult::from_diagnostics(diagnostics, None, None);
+        assert_eq!(result.outcome, DetectOutcome::Inconclusive);
+        assert!(!result.is_conclusive());
+        assert_eq!(result.diagnostics.len(), 2);
+
+        // a match is conclusive even if another strategy failed
+        let diagnostics = vec![
+            StrategyDiagnostic {
+                strategy: "eip_897",
+                outcome: StrategyOutcome::Error("request timed out".to_string()),
+            },
+            StrategyDiagnostic::new(
+                "eip_1967_logic",
+                Ok(Some(StrategyMatch { proxy_type: ProxyType::Eip1967Logic, target })),
+            ),
+        ];
+        let result = DetectResult::from_diagnostics(diagnostics, None, None);
+        assert_eq!(result.outcome, DetectOutcome::Proxy);
+        assert_eq!(result.target, Some(target));
+
+        // chain data that rules the strategy out is a conclusive "no"
+        let parse_error = StrategyDiagnostic::new("eip_1167", Err(Mismatch("Invalid EIP-1167 bytecode suffix").into()));
+        assert!(matches!(parse_error.outcome, StrategyOutcome::NoMatch));
+        let decode_error = facetsCall::abi_decode_returns(&[0u8; 4], true).err().expect("decoded");
+        let diagnostic = StrategyDiagnostic::new("eip_2535", Err(decode_error.into()));
+        assert!(matches!(diagnostic.outcome, StrategyOutcome::NoMatch));
+        // anything else may go away on a retry
+        let unknown = StrategyDiagnostic::new("eip_1167", Err(eyre!("unexpected response")));
+        assert!(matches!(unknown.outcome, StrategyOutcome::Error(_)));
+        let rpc_error: TransportError = TransportError::local_usage_str("connection refused");
+        let diagnostic = StrategyDiagnostic::new("eip_897", Err(rpc_error.into()));
+        assert!(matches!(diagnostic.outcome, StrategyOutcome::Error(_)));
+    }
+
+    // tests/fixtures/proxy_strategies.json, proxies are 0xa1..<n>, implementations 0xb1..<n>
+    fn fixture_address(prefix: &str, n: u8) -> Address {
+        format!("0x{}{}{:02x}", prefix, "00".repeat(18), n).parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_strategies_offline() {
+        let server = MockServer::start(1, "proxy_strategies").await.expect("mock server not started");
+        let detector = ProxyDetector::new(&server.chain()).await.expect("detector not created");
+
+        let strategies = [
+            (1, ProxyType::Eip1167),
+            (2, ProxyType::Erc7511),
+            (3, ProxyType::Eip1967Beacon),
+            (4, ProxyType::OpenZeppelin),
+            (5, ProxyType::Eip1822),
+            (6, ProxyType::Eip897),
+            (7, ProxyType::Eip1967Logic),
+            (8, ProxyType::GnosisSafe),
+            (9, ProxyType::Compound),
+            (10, ProxyType::Erc1967Minimal),
+            (11, ProxyType::GnosisSafeSlot),
+        ];
+        for (n, proxy_type) in strategies {
+            let proxy = format!("{:#x}", fixture_address("a1", n));
+            let result = detector.detect_proxy_target(&proxy).await.unwrap();
+            let implementation = fixture_address("b1", n);
+            assert_eq!(result.outcome, DetectOutcome::Proxy, "{} {:?}", proxy_type, result.diagnostics);
+            assert!(
+                result.matches.iter().any(|m| m.proxy_type == proxy_type && m.target == implementation),
+                "{} not matched: {:?}",
+                proxy_type,
+                result.matches
+            );
+            assert_eq!(result.target, Some(implementation));
+            assert!(!result.conflict);
+        }
+
+        let beacon = detector.detect_proxy_target(&format!("{:#x}", fixture_address("a1", 3))).await.unwrap();
+        assert_eq!(beacon.beacon, Some(fixture_address("c1", 3)));
+        let logic = detector.detect_proxy_target(&format!("{:#x}", fixture_address("a1", 7))).await.unwrap();
+        assert_eq!(logic.admin, Some(fixture_address("ad", 7)));
+
+        // diamond answering the facets() loupe call
+        let diamond = detector.detect_proxy_target(&format!("{:#x}", fixture_address("a1", 12))).await.unwrap();
+        assert_eq!(diamond.proxy_type, Some(ProxyType::Eip2535));
+        let facets = diamond.facets.expect("facets not found");
+        assert_eq!(facets.get("0xa9059cbb"), Some(&fixture_address("f1", 1)));
+        assert_eq!(facets.get("0x23b872dd"), Some(&fixture_address("f1", 2)));
+        assert_eq!(facets.len(), 3);
+
+        // diamond rebuilt from its DiamondCut events
+        let diamond = detector.detect_proxy_target(&format!("{:#x}", fixture_address("a1", 13))).await.unwrap();
+        let facets = diamond.facets.expect("facets not found");
+        assert_eq!(facets.get("0x095ea7b3"), Some(&fixture_address("f2", 2)));
+        assert_eq!(facets.get("0xa9059cbb"), None);
+
+        // diamond loupe without a known deployment, read facet by facet
+        let diamond = fixture_address("a1", 17);
+        let result = detector.detect_proxy_target(&format!("{:#x}", diamond)).await.unwrap();
+        assert_eq!(result.proxy_type, Some(ProxyType::Eip2535));
+        let facets = result.facets.expect("facets not found");
+        assert_eq!(facets.get("0xa9059cbb"), Some(&fixture_address("f3", 1)));
+        let facet = detector.facet_address(diamond, "0xa9059cbb", BlockId::latest()).await.unwrap();
+        assert_eq!(facet, Some(fixture_address("f3", 1)));
+
+        // the node failed the facets() call, the diamond may still be one
+        let failing_diamond = fixture_address("a1", 18);
+        let result = detector.detect_proxy_target(&format!("{:#x}", failing_diamond)).await.unwrap();
+        assert_eq!(result.outcome, DetectOutcome::Inconclusive, "{:?}", result.diagnostics);
+        assert!(ProxyDetector::cached(&CacheKey::new(1, failing_diamond)).is_none());
+
+        let not_proxy = fixture_address("a1", 14);
+        let result = detector.detect_proxy_target(&format!("{:#x}", not_proxy)).await.unwrap();
+        assert_eq!(result.outcome, DetectOutcome::NotProxy, "{:?}", result.diagnostics);
+
+        // the node failed instead of reverting, nothing is cached
+        let failing = fixture_address("a1", 15);
+        let result = detector.detect_proxy_target(&format!("{:#x}", failing)).await.unwrap();
+        assert_eq!(result.outcome, DetectOutcome::Inconclusive);
+        assert!(ProxyDetector::cached(&CacheKey::new(1, failing)).is_none());
+
+        // the implementation slot is only set from block 100
+        let upgraded = format!("{:#x}", fixture_address("a1", 16));
+        let result = detector.detect_proxy_target_at(&upgraded, 100).await.unwrap();
+        assert_eq!(result.proxy_type, Some(ProxyType::Eip1967Logic));
+        assert_eq!(result.target, Some(fixture_address("b1", 16)));
+        let result = detector.detect_proxy_target_at(&upgraded, 99).await.unwrap();
+        assert_eq!(result.outcome, DetectOutcome::NotProxy);
+
+        let history = ProxyDetector::cached_history(&CacheKey::new(1, fixture_address("a1", 16)));
+        assert!(history.iter().any(|entry| entry.from_block == 99 && entry.to_block == 99));
+
+        // a clone holds up to the latest block 100
+        let clone = fixture_address("a1", 1);
+        detector.detect_proxy_target_at(&format!("{:#x}", clone), 50).await.unwrap();
+        let history = ProxyDetector::cached_history(&CacheKey::new(1, clone));
+        assert!(history.iter().any(|entry| entry.from_block == 50 && entry.to_block == 100), "{:?}", history);
+
+        // the beacon upgrades at block 80
+        let beacon_proxy = fixture_address("a1", 3);
+        detector.detect_proxy_target_at(&format!("{:#x}", beacon_proxy), 50).await.unwrap();
+        let history = ProxyDetector::cached_history(&CacheKey::new(1, beacon_proxy));
+        assert!(history.iter().any(|entry| entry.from_block == 50 && entry.to_block == 79), "{:?}", history);
+
+        // nothing logs an EIP-897 upgrade
+        let eip_897 = fixture_address("a1", 6);
+        detector.detect_proxy_target_at(&format!("{:#x}", eip_897), 50).await.unwrap();
+        let history = ProxyDetector::cached_history(&CacheKey::new(1, eip_897));
+        assert!(history.iter().any(|entry| entry.from_block == 50 && entry.to_block == 50));
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the anvil binary, run with --ignored"]
+    async fn test_strategies_anvil() {
+        let harness = AnvilHarness::start().await.expect("anvil not started");
+        let detector = ProxyDetector::new(&harness.chain()).await.expect("detector not created");
+
+        let implementation = fixture_address("d1", 1);
+        let (plain, eip_1967, beacon_proxy, beacon, clone, diamond) = (
+            fixture_address("d0", 1),
+            fixture_address("d0", 2),
+            fixture_address("d0", 3),
+            fixture_address("dc", 3),
+            fixture_address("d0", 4),
+            fixture_address("d0", 5),
+        );
+        harness.deploy_plain(implementation).await.unwrap();
+        harness.deploy_plain(plain).await.unwrap();
+        harness.deploy_eip_1967_proxy(eip_1967, implementation).await.unwrap();
+        harness.deploy_beacon_proxy(beacon_proxy, beacon, implementation).await.unwrap();
+        harness.deploy_clone(clone, implementation).await.unwrap();
+        let transfer = FixedBytes::from([0xa9, 0x05, 0x9c, 0xbb]);
+        harness.deploy_diamond(diamond, &[(implementation, vec![transfer])]).await.unwrap();
+
+        let detect = |address: Address| {
+            let detector = &detector;
+            async move { detector.detect_proxy_target(&format!("{:#x}", address)).await.unwrap() }
+        };
+
+        assert_eq!(detect(plain).await.outcome, DetectOutcome::NotProxy);
+
+        let result = detect(eip_1967).await;
+        assert_eq!(result.target, Some(implementation));
+        assert!(result.matches.iter().any(|m| m.proxy_type == ProxyType::Eip1967Logic));
+
+        let result = detect(beacon_proxy).await;
+        assert_eq!(result.proxy_type, Some(ProxyType::Eip1967Beacon));
+        assert_eq!(result.target, Some(implementation));
+        assert_eq!(result.beacon, Some(beacon));
+
+        let result = detect(clone).await;
+        assert_eq!(result.proxy_type, Some(ProxyType::Eip1167));
+        assert_eq!(result.target, Some(implementation));
+
+        let result = detect(diamond).await;
+        assert_eq!(result.proxy_type, Some(ProxyType::Eip2535));
+        assert_eq!(result.facets.unwrap().get("0xa9059cbb"), Some(&implementation));
+    }
 }
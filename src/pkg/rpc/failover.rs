@@ -0,0 +1,437 @@
+use crate::pkg::config::config::{redact_url, ChainConfig};
+use crate::pkg::telemetry::metrics;
+use alloy::rpc::client::BuiltInConnectionString;
+use alloy::rpc::json_rpc::{RequestPacket, Response, ResponsePacket, ResponsePayload};
+use alloy::transports::{BoxTransport, TransportError, TransportErrorKind, TransportFut};
+use eyre::{eyre, Result};
+use futures::future::join_all;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::Service;
+use tracing::warn;
+
+const DEFAULT_RPC_TIMEOUT: u64 = 10;
+// reads the proxy detection takes an implementation address from
+const QUORUM_METHODS: [&str; 2] = ["eth_getStorageAt", "eth_call"];
+// weight of the latest outcome in the health score
+const SCORE_ALPHA: f64 = 0.2;
+// a low score only shrinks the share of an endpoint, it never drops to zero
+const MIN_SCORE: f64 = 0.05;
+// endpoints are skipped after a failure, doubling up to the max with every failure in a row
+const BASE_COOLDOWN: Duration = Duration::from_secs(5);
+const MAX_COOLDOWN: Duration = Duration::from_secs(300);
+
+lazy_static! {
+    // health of the endpoints by chain id and url, outlives the per-request clients
+    static ref HEALTH: Mutex<HashMap<(u64, String), EndpointHealth>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointHealth {
+    pub score: f64,        // 1 when every call succeeds, moving average of the outcomes
+    pub latency_ms: f64,   // moving average of the successful calls
+    pub consecutive_failures: u32,
+    pub cooldown_until: Option<Instant>,
+    current_weight: f64,   // smooth weighted round robin state
+}
+
+impl Default for EndpointHealth {
+    fn default() -> Self {
+        EndpointHealth {
+            score: 1.0,
+            latency_ms: 0.0,
+            consecutive_failures: 0,
+            cooldown_until: None,
+            current_weight: 0.0,
+        }
+    }
+}
+
+impl EndpointHealth {
+    fn record_success(&mut self, latency: Duration) {
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+        self.latency_ms = if self.latency_ms == 0.0 {
+            latency_ms
+        } else {
+            self.latency_ms * (1.0 - SCORE_ALPHA) + latency_ms * SCORE_ALPHA
+        };
+        self.score = self.score * (1.0 - SCORE_ALPHA) + SCORE_ALPHA;
+        self.consecutive_failures = 0;
+        self.cooldown_until = None;
+    }
+
+    fn record_failure(&mut self, now: Instant) {
+        self.score *= 1.0 - SCORE_ALPHA;
+        self.consecutive_failures += 1;
+        let cooldown = BASE_COOLDOWN * 2u32.saturating_pow(self.consecutive_failures - 1);
+        self.cooldown_until = Some(now + cooldown.min(MAX_COOLDOWN));
+    }
+
+    fn cooling_down(&self, now: Instant) -> bool {
+        self.cooldown_until.is_some_and(|until| until > now)
+    }
+}
+
+struct Endpoint {
+    url: String,
+    weight: u32,
+    transport: BoxTransport,
+}
+
+struct Inner {
+    chain_id: u64,
+    chain: String, // name, the label of the metrics
+    endpoints: Vec<Endpoint>,
+    quorum: Option<usize>,
+    timeout: Duration,
+}
+
+// transport over every rpc endpoint of a chain. a call goes to the endpoint picked by weight
+// and health, and on to the next one on an error, a timeout or a rate limit. with a quorum,
+// storage reads and calls go to every endpoint and `quorum` of them have to give the same answer.
+#[derive(Clone)]
+pub struct FailoverTransport {
+    inner: Arc<Inner>,
+}
+
+impl FailoverTransport {
+    pub async fn connect(chain: &ChainConfig, quorum: Option<usize>) -> Result<Self> {
+        let mut endpoints = vec![];
+        let mut errors = vec![];
+        for endpoint in chain.all_rpc_endpoints() {
+            let transport = match endpoint.url.parse::<BuiltInConnectionString>() {
+                Ok(connect) => connect.connect_boxed().await,
+                Err(e) => Err(e),
+            };
+            match transport {
+                Ok(transport) => endpoints.push(Endpoint {
+                    url: endpoint.url,
+                    weight: endpoint.weight,
+                    transport,
+                }),
+                Err(e) => {
+                    record(chain.id, &endpoint.url, Err(()));
+                    errors.push(format!("{}: {}", redact_url(&endpoint.url), e));
+                }
+            }
+        }
+
+        if endpoints.is_empty() {
+            return Err(eyre!("no rpc endpoint of chain {} connected: {}", chain.name, errors.join(", ")));
+        }
+        if !errors.is_empty() {
+            warn!(chain = %chain.name, errors = %errors.join(", "), "rpc endpoints skipped");
+        }
+
+        Ok(FailoverTransport {
+            inner: Arc::new(Inner {
+                chain_id: chain.id,
+                chain: chain.name.clone(),
+                endpoints,
+                quorum,
+                timeout: Duration::from_secs(chain.rpc_timeout.unwrap_or(DEFAULT_RPC_TIMEOUT)),
+            }),
+        })
+    }
+}
+
+impl Service<RequestPacket> for FailoverTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let inner = self.inner.clone();
+        Box::pin(async move { inner.dispatch(request).await })
+    }
+}
+
+impl Inner {
+    async fn dispatch(&self, request: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        if let Some(quorum) = self.quorum.filter(|_| is_quorum_request(&request)) {
+            return self.dispatch_quorum(request, quorum).await;
+        }
+
+        let mut last_error = None;
+        for index in self.endpoint_order() {
+            match self.send(index, request.clone()).await {
+                Ok(response) => return Ok(response),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| TransportErrorKind::custom_str("no rpc endpoint")))
+    }
+
+    async fn dispatch_quorum(&self, request: RequestPacket, quorum: usize) -> Result<ResponsePacket, TransportError> {
+        let order = self.endpoint_order();
+        let responses = join_all(order.iter().map(|&index| self.send(index, request.clone()))).await;
+        let answers: Vec<(usize, String, ResponsePacket)> = order
+            .into_iter()
+            .zip(responses)
+            .filter_map(|(index, response)| response.ok().map(|response| (index, answer_key(&response), response)))
+            .collect();
+
+        let votes = |key: &str| answers.iter().filter(|(_, answer, _)| answer == key).count();
+        let Some(winner) = answers.iter().find(|(_, key, _)| votes(key) >= quorum) else {
+            return Err(TransportErrorKind::custom_str(&format!(
+                "no {} of the rpc endpoints agree, {} answered",
+                quorum,
+                answers.len()
+            )));
+        };
+
+        // the endpoints that disagree with the quorum lose health like a failed call
+        for (index, key, _) in &answers {
+            if *key != winner.1 {
+                warn!(
+                    chain_id = self.chain_id,
+                    endpoint = %redact_url(&self.endpoints[*index].url),
+                    "rpc endpoint disagrees with the quorum"
+                );
+                record(self.chain_id, &self.endpoints[*index].url, Err(()));
+            }
+        }
+        Ok(winner.2.clone())
+    }
+
+    // a single call bounded by the timeout, its outcome goes into the health of the endpoint
+    async fn send(&self, index: usize, request: RequestPacket) -> Result<ResponsePacket, TransportError> {
+        let endpoint = &self.endpoints[index];
+        let mut transport = endpoint.transport.clone();
+        let started = Instant::now();
+        let mut rate_limited = false;
+        let result = match tokio::time::timeout(self.timeout, transport.call(request)).await {
+            Ok(Ok(response)) if is_rate_limited(&response) => {
+                rate_limited = true;
+                Err(TransportErrorKind::custom_str("rate limited"))
+            }
+            Ok(result) => result,
+            Err(_) => Err(TransportErrorKind::custom_str(&format!("timed out after {:?}", self.timeout))),
+        };
+        let cooldown = record(
+            self.chain_id,
+            &endpoint.url,
+            result.as_ref().map(|_| started.elapsed()).map_err(|_| ()),
+        );
+        // the cooldown is how long the rate limit keeps the endpoint out of the rotation
+        if let Some(cooldown) = cooldown.filter(|_| rate_limited) {
+            metrics::observe_rate_limit_cooldown(&self.chain, cooldown);
+        }
+        if let Err(e) = &result {
+            warn!(chain_id = self.chain_id, endpoint = %redact_url(&endpoint.url), error = %e, "rpc endpoint failed");
+        }
+        result
+    }
+
+    fn endpoint_order(&self) -> Vec<usize> {
+        let mut health = HEALTH.lock().unwrap();
+        let mut states: Vec<EndpointHealth> = self
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                health
+                    .get(&(self.chain_id, endpoint.url.clone()))
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect();
+        let weights: Vec<u32> = self.endpoints.iter().map(|endpoint| endpoint.weight).collect();
+        let order = order(&mut states, &weights, Instant::now());
+        for (endpoint, state) in self.endpoints.iter().zip(states) {
+            health.insert((self.chain_id, endpoint.url.clone()), state);
+        }
+        order
+    }
+}
+
+// returns the cooldown the endpoint is put in after a failure
+fn record(chain_id: u64, url: &str, outcome: std::result::Result<Duration, ()>) -> Option<Duration> {
+    let mut health = HEALTH.lock().unwrap();
+    let state = health.entry((chain_id, url.to_string())).or_default();
+    let now = Instant::now();
+    match outcome {
+        Ok(latency) => state.record_success(latency),
+        Err(()) => state.record_failure(now),
+    }
+    state.cooldown_until.map(|until| until.saturating_duration_since(now))
+}
+
+// health of the endpoints of a chain that were called so far, by url
+pub fn endpoint_health(chain_id: u64) -> Vec<(String, EndpointHealth)> {
+    HEALTH
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|((id, _), _)| *id == chain_id)
+        .map(|((_, url), state)| (url.clone(), state.clone()))
+        .collect()
+}
+
+// smooth weighted round robin over the endpoints out of cooldown, weighted by weight * score,
+// for the first endpoint. the others follow by score, the ones cooling down as a last resort.
+fn order(states: &mut [EndpointHealth], weights: &[u32], now: Instant) -> Vec<usize> {
+    let effective = |state: &EndpointHealth, weight: u32| weight as f64 * state.score.max(MIN_SCORE);
+    let available: Vec<usize> = (0..states.len())
+        .filter(|&index| weights[index] > 0 && !states[index].cooling_down(now))
+        .collect();
+
+    let mut order = vec![];
+    if !available.is_empty() {
+        let total: f64 = available.iter().map(|&index| effective(&states[index], weights[index])).sum();
+        for &index in &available {
+            states[index].current_weight += effective(&states[index], weights[index]);
+        }
+        let picked = available
+            .iter()
+            .copied()
+            .reduce(|best, index| {
+                if states[index].current_weight > states[best].current_weight {
+                    index
+                } else {
+                    best
+                }
+            })
+            .unwrap_or_default();
+        states[picked].current_weight -= total;
+        order.push(picked);
+    }
+
+    let mut rest: Vec<usize> = (0..states.len()).filter(|index| !order.contains(index)).collect();
+    rest.sort_by(|&a, &b| {
+        states[a]
+            .cooling_down(now)
+            .cmp(&states[b].cooling_down(now))
+            .then(states[b].score.total_cmp(&states[a].score))
+    });
+    order.extend(rest);
+    order
+}
+
+fn is_quorum_request(request: &RequestPacket) -> bool {
+    match request {
+        RequestPacket::Single(request) => QUORUM_METHODS.contains(&request.method()),
+        RequestPacket::Batch(_) => false,
+    }
+}
+
+fn responses(response: &ResponsePacket) -> &[Response] {
+    match response {
+        ResponsePacket::Single(response) => std::slice::from_ref(response),
+        ResponsePacket::Batch(responses) => responses,
+    }
+}
+
+fn is_rate_limited(response: &ResponsePacket) -> bool {
+    responses(response)
+        .iter()
+        .filter_map(|response| response.payload.as_error())
+        .any(|error| {
+            error.code == 429 || error.code == -32005 || error.message.to_lowercase().contains("rate limit")
+        })
+}
+
+// what the endpoints vote on, hex is compared case-insensitively
+fn answer_key(response: &ResponsePacket) -> String {
+    responses(response)
+        .iter()
+        .map(|response| match &response.payload {
+            ResponsePayload::Success(result) => format!("result:{}", result.get().to_lowercase()),
+            ResponsePayload::Failure(error) => format!("error:{}:{}", error.code, error.message),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pkg::config::client::{new_quorum_rpc_client, new_rpc_client};
+    use crate::pkg::config::config::RpcEndpoint;
+    use crate::pkg::mock::server::MockServer;
+    use alloy::primitives::{Address, B256, U256};
+
+    #[test]
+    fn test_order() {
+        let now = Instant::now();
+        let mut states = vec![EndpointHealth::default(), EndpointHealth::default()];
+        let picks: Vec<usize> = (0..6).map(|_| order(&mut states, &[2, 1], now)[0]).collect();
+        assert_eq!(picks.iter().filter(|&&index| index == 0).count(), 4);
+        assert_eq!(picks.iter().filter(|&&index| index == 1).count(), 2);
+
+        // a failed endpoint is skipped during its cooldown but stays the last resort
+        states[0].record_failure(now);
+        assert_eq!(order(&mut states, &[2, 1], now), vec![1, 0]);
+        assert!(!states[0].cooling_down(now + MAX_COOLDOWN));
+    }
+
+    #[test]
+    fn test_health() {
+        let now = Instant::now();
+        let mut health = EndpointHealth::default();
+        health.record_failure(now);
+        health.record_failure(now);
+        assert_eq!(health.consecutive_failures, 2);
+        assert_eq!(health.cooldown_until, Some(now + BASE_COOLDOWN * 2));
+        assert!(health.score < 0.7);
+
+        health.record_success(Duration::from_millis(40));
+        assert!(!health.cooling_down(now));
+        assert_eq!(health.consecutive_failures, 0);
+        assert_eq!(health.latency_ms, 40.0);
+    }
+
+    fn endpoint(url: String) -> RpcEndpoint {
+        RpcEndpoint { url, weight: 1 }
+    }
+
+    #[tokio::test]
+    async fn test_failover_offline() {
+        let server = MockServer::start(1, "erc20_token").await.expect("mock server not started");
+        let mut chain = server.chain();
+        // nothing listens on port 1
+        chain.rpc_endpoints = vec![endpoint("http://127.0.0.1:1/rpc".to_string())];
+        chain.rpc_timeout = Some(2);
+
+        let provider = new_rpc_client(&chain).await.unwrap();
+        for _ in 0..3 {
+            assert_eq!(provider.get_block_number().await.unwrap(), 100);
+        }
+        let health = endpoint_health(chain.id);
+        let (_, dead) = health.iter().find(|(url, _)| url.contains(":1/")).expect("dead endpoint not called");
+        assert_eq!(dead.consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_quorum_offline() {
+        let proxies = MockServer::start(1, "proxy_strategies").await.expect("mock server not started");
+        let tokens = MockServer::start(1, "erc20_token").await.expect("mock server not started");
+        let proxy: Address = "0xa100000000000000000000000000000000000007".parse().unwrap();
+        let slot = U256::from_str_radix("360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc", 16).unwrap();
+
+        // the erc20 mock has nothing in the slot and is outvoted
+        let mut chain = proxies.chain();
+        chain.rpc_endpoints = vec![endpoint(proxies.chain().rpc), endpoint(tokens.chain().rpc)];
+        chain.rpc_quorum = Some(2);
+        let provider = new_quorum_rpc_client(&chain).await.unwrap();
+        let storage = provider.get_storage_at(proxy, slot).await.unwrap();
+        let implementation: Address = "0xb100000000000000000000000000000000000007".parse().unwrap();
+        assert_eq!(B256::from(storage), implementation.into_word());
+
+        // one of two endpoints is no quorum
+        let mut chain = tokens.chain();
+        chain.rpc_endpoints = vec![endpoint(proxies.chain().rpc)];
+        chain.rpc_quorum = Some(2);
+        let provider = new_quorum_rpc_client(&chain).await.unwrap();
+        assert!(provider.get_storage_at(proxy, slot).await.is_err());
+
+        // other methods are not voted on
+        assert_eq!(provider.get_block_number().await.unwrap(), 100);
+    }
+}
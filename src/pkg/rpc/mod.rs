@@ -0,0 +1,2 @@
+pub mod failover;
+pub mod trace;
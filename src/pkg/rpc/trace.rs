@@ -0,0 +1,82 @@
+use crate::pkg::telemetry::metrics;
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::{BoxTransport, TransportError, TransportFut};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tower::Service;
+use tracing::{debug, info_span, warn, Instrument};
+
+// transport that puts every JSON-RPC call of a chain in an `rpc` span, logs its latency and
+// outcome and counts it in the metrics. the outcome is ok, rpc_error (an error answer, e.g. a
+// revert) or error (the call failed)
+#[derive(Clone)]
+pub struct TracedTransport {
+    inner: BoxTransport,
+    chain: Arc<str>,
+}
+
+impl TracedTransport {
+    pub fn new(chain: &str, inner: BoxTransport) -> Self {
+        TracedTransport {
+            inner,
+            chain: chain.into(),
+        }
+    }
+}
+
+impl Service<RequestPacket> for TracedTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let method = method(&request);
+        let span = info_span!("rpc", chain = %self.chain, method = %method);
+        let chain = self.chain.clone();
+        let started = Instant::now();
+        let response = self.inner.call(request);
+        Box::pin(
+            async move {
+                let result = response.await;
+                let latency = started.elapsed();
+                let latency_ms = latency.as_millis() as u64;
+                match &result {
+                    Ok(response) => {
+                        debug!(latency_ms, outcome = outcome(response), "rpc call");
+                        metrics::observe_rpc_call(&chain, &method, outcome(response), latency);
+                    }
+                    Err(e) => {
+                        warn!(latency_ms, outcome = "error", error = %e, "rpc call failed");
+                        metrics::observe_rpc_call(&chain, &method, "error", latency);
+                    }
+                }
+                result
+            }
+            .instrument(span),
+        )
+    }
+}
+
+pub fn method(request: &RequestPacket) -> String {
+    match request {
+        RequestPacket::Single(request) => request.method().to_string(),
+        RequestPacket::Batch(_) => "batch".to_string(),
+    }
+}
+
+pub fn outcome(response: &ResponsePacket) -> &'static str {
+    let is_error = match response {
+        ResponsePacket::Single(response) => response.is_error(),
+        ResponsePacket::Batch(responses) => responses.iter().any(|response| response.is_error()),
+    };
+    if is_error {
+        "rpc_error"
+    } else {
+        "ok"
+    }
+}
@@ -0,0 +1,18 @@
+use crate::pkg::config::config::{LogConfig, LogFormat};
+use tracing_subscriber::EnvFilter;
+
+// install the global subscriber. RUST_LOG takes precedence over the configured level.
+// the logs go to stderr so the stdout of the binaries stays their output.
+pub fn init(config: &LogConfig) {
+    let filter = EnvFilter::try_from_default_env()
+        .or_else(|_| EnvFilter::try_new(&config.level))
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    // keep the subscriber installed first when there is one
+    let _ = match config.format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().with_current_span(true).with_span_list(false).try_init(),
+    };
+}
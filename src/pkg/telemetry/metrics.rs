@@ -0,0 +1,165 @@
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge_vec, Encoder, HistogramVec, IntCounterVec,
+    IntGaugeVec, TextEncoder,
+};
+use std::time::Duration;
+
+// a sampling request scans pages of transactions and hydrates each sample, so it takes
+// seconds to minutes. the rpc and explorer calls use the default buckets (5ms to 10s).
+const HTTP_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+// there is no client side rate limiter, so no limiter wait time is measured. the closest
+// signal is the failover cooldown of an rpc endpoint that answered with a rate limit.
+const RATE_LIMIT_COOLDOWN_BUCKETS: &[f64] = &[5.0, 10.0, 20.0, 40.0, 80.0, 160.0, 300.0];
+
+lazy_static! {
+    static ref HTTP_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "sampler_http_requests_total",
+        "HTTP requests by route and status",
+        &["route", "status"]
+    )
+    .unwrap();
+    static ref HTTP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "sampler_http_request_duration_seconds",
+        "HTTP request latency by route",
+        &["route"],
+        HTTP_BUCKETS.to_vec()
+    )
+    .unwrap();
+    static ref RPC_CALLS: IntCounterVec = register_int_counter_vec!(
+        "sampler_rpc_calls_total",
+        "JSON-RPC calls by chain, method and outcome (ok, rpc_error, error)",
+        &["chain", "method", "outcome"]
+    )
+    .unwrap();
+    static ref RPC_CALL_DURATION: HistogramVec = register_histogram_vec!(
+        "sampler_rpc_call_duration_seconds",
+        "JSON-RPC call latency by chain and method",
+        &["chain", "method"]
+    )
+    .unwrap();
+    static ref EXPLORER_CALLS: IntCounterVec = register_int_counter_vec!(
+        "sampler_explorer_calls_total",
+        "explorer API calls by chain, action and outcome (ok, rate_limited, error)",
+        &["chain", "action", "outcome"]
+    )
+    .unwrap();
+    static ref EXPLORER_CALL_DURATION: HistogramVec = register_histogram_vec!(
+        "sampler_explorer_call_duration_seconds",
+        "explorer API call latency by chain and action",
+        &["chain", "action"]
+    )
+    .unwrap();
+    static ref CACHE_LOOKUPS: IntCounterVec = register_int_counter_vec!(
+        "sampler_cache_lookups_total",
+        "cache lookups by cache (abi, proxy, proxy_history) and result (hit, miss)",
+        &["cache", "result"]
+    )
+    .unwrap();
+    static ref RATE_LIMIT_COOLDOWN: HistogramVec = register_histogram_vec!(
+        "sampler_rpc_rate_limit_cooldown_seconds",
+        "failover cooldown of a rate limited rpc endpoint by chain, not a rate limiter wait",
+        &["chain"],
+        RATE_LIMIT_COOLDOWN_BUCKETS.to_vec()
+    )
+    .unwrap();
+    static ref JOBS_IN_FLIGHT: IntGaugeVec = register_int_gauge_vec!(
+        "sampler_jobs_in_flight",
+        "sampling and coverage jobs running",
+        &["kind"]
+    )
+    .unwrap();
+}
+
+pub fn observe_request(route: &str, status: u16, latency: Duration) {
+    HTTP_REQUESTS.with_label_values(&[route, &status.to_string()]).inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&[route])
+        .observe(latency.as_secs_f64());
+}
+
+pub fn observe_rpc_call(chain: &str, method: &str, outcome: &str, latency: Duration) {
+    RPC_CALLS.with_label_values(&[chain, method, outcome]).inc();
+    RPC_CALL_DURATION
+        .with_label_values(&[chain, method])
+        .observe(latency.as_secs_f64());
+}
+
+pub fn observe_explorer_call(chain: &str, action: &str, outcome: &str, latency: Duration) {
+    EXPLORER_CALLS.with_label_values(&[chain, action, outcome]).inc();
+    EXPLORER_CALL_DURATION
+        .with_label_values(&[chain, action])
+        .observe(latency.as_secs_f64());
+}
+
+pub fn observe_cache_lookup(cache: &str, hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    CACHE_LOOKUPS.with_label_values(&[cache, result]).inc();
+}
+
+pub fn observe_rate_limit_cooldown(chain: &str, cooldown: Duration) {
+    RATE_LIMIT_COOLDOWN
+        .with_label_values(&[chain])
+        .observe(cooldown.as_secs_f64());
+}
+
+// counts a job as in flight until dropped, so failed and cancelled jobs are counted out too
+pub struct JobGuard {
+    kind: &'static str,
+}
+
+impl JobGuard {
+    pub fn start(kind: &'static str) -> Self {
+        JOBS_IN_FLIGHT.with_label_values(&[kind]).inc();
+        JobGuard { kind }
+    }
+}
+
+impl Drop for JobGuard {
+    fn drop(&mut self) {
+        JOBS_IN_FLIGHT.with_label_values(&[self.kind]).dec();
+    }
+}
+
+// every registered metric in the Prometheus text format
+pub fn render() -> String {
+    let mut buffer = vec![];
+    if let Err(e) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        return format!("# failed to encode metrics: {}\n", e);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        observe_request("/sample", 200, Duration::from_millis(1500));
+        observe_rpc_call("test-render", "eth_call", "ok", Duration::from_millis(20));
+        observe_explorer_call("test-render", "txlist", "rate_limited", Duration::from_millis(300));
+        observe_cache_lookup("test-render", true);
+        observe_rate_limit_cooldown("test-render", Duration::from_secs(10));
+
+        let metrics = render();
+        assert!(metrics.contains(r#"sampler_rpc_calls_total{chain="test-render",method="eth_call",outcome="ok"} 1"#));
+        assert!(metrics.contains(
+            r#"sampler_explorer_calls_total{action="txlist",chain="test-render",outcome="rate_limited"} 1"#
+        ));
+        assert!(metrics.contains(r#"sampler_cache_lookups_total{cache="test-render",result="hit"} 1"#));
+        assert!(metrics.contains(r#"sampler_rpc_rate_limit_cooldown_seconds_bucket{chain="test-render",le="10"} 1"#));
+        assert!(metrics.contains(r#"sampler_http_request_duration_seconds_bucket{route="/sample",le="2.5"}"#));
+    }
+
+    #[test]
+    fn test_job_guard() {
+        let jobs = || JOBS_IN_FLIGHT.with_label_values(&["test-guard"]).get();
+        let guard = JobGuard::start("test-guard");
+        let other = JobGuard::start("test-guard");
+        assert_eq!(jobs(), 2);
+        drop(guard);
+        drop(other);
+        assert_eq!(jobs(), 0);
+    }
+}
@@ -0,0 +1,249 @@
+use crate::model::evm::resolved_function_event_map;
+use crate::pkg::config::client::*;
+use crate::pkg::proxy::proxy::ProxyDetector;
+use crate::pkg::telemetry::metrics::JobGuard;
+use crate::sampler::sampler::{Sampler, TX_PAGE_SIZE};
+use crate::sampler::window::SampleWindow;
+use alloy::json_abi::{Function, StateMutability};
+use alloy::primitives::Address;
+use alloy::rpc::types::Filter;
+use eyre::Result;
+use std::collections::{HashMap, HashSet};
+use tracing::instrument;
+
+// a coverage log scan covers at most this many eth_getLogs chunks before the end of the window
+const MAX_LOG_SCAN_CHUNKS: u64 = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverageKind {
+    Function,
+    Event,
+}
+
+// one occurrence of a selector (method id or event topic0) in the scanned window
+#[derive(Debug, Clone)]
+pub struct Observation {
+    pub selector: String,
+    pub block_number: u64,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct CoverageEntry {
+    pub kind: CoverageKind,
+    pub selector: String,          // e.g. 0x88316456
+    pub signature: Option<String>, // None if the selector is observed but not declared in the ABI
+    pub observed_count: u64,
+    pub first_seen_block: Option<u64>,
+    pub last_seen_block: Option<u64>,
+    pub sample_hash: Option<String>,
+}
+
+impl CoverageEntry {
+    pub fn observed(&self) -> bool {
+        self.observed_count > 0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CoverageReport {
+    pub address: String,
+    pub implementation: Option<String>,
+    pub from_block: u64,
+    pub to_block: u64,
+    pub functions: Vec<CoverageEntry>,
+    pub events: Vec<CoverageEntry>,
+}
+
+impl CoverageReport {
+    // merge the declared ABI items with the observed selectors.
+    // declared items come first (sorted by signature), undeclared observed selectors last.
+    pub fn build_entries(
+        kind: CoverageKind,
+        declared: &HashMap<String, String>,
+        observations: &[Observation],
+    ) -> Vec<CoverageEntry> {
+        let mut entries: HashMap<String, CoverageEntry> = declared
+            .iter()
+            .map(|(selector, signature)| {
+                (
+                    selector.clone(),
+                    CoverageEntry {
+                        kind,
+                        selector: selector.clone(),
+                        signature: Some(signature.clone()),
+                        observed_count: 0,
+                        first_seen_block: None,
+                        last_seen_block: None,
+                        sample_hash: None,
+                    },
+                )
+            })
+            .collect();
+
+        for observation in observations {
+            let entry = entries
+                .entry(observation.selector.clone())
+                .or_insert_with(|| CoverageEntry {
+                    kind,
+                    selector: observation.selector.clone(),
+                    signature: None,
+                    observed_count: 0,
+                    first_seen_block: None,
+                    last_seen_block: None,
+                    sample_hash: None,
+                });
+
+            entry.observed_count += 1;
+            if entry.first_seen_block.is_none_or(|b| observation.block_number < b) {
+                entry.first_seen_block = Some(observation.block_number);
+            }
+            if entry.last_seen_block.is_none_or(|b| observation.block_number >= b) {
+                entry.last_seen_block = Some(observation.block_number);
+                entry.sample_hash = Some(observation.hash.clone());
+            }
+        }
+
+        let mut result: Vec<CoverageEntry> = entries.into_values().collect();
+        result.sort_by(|a, b| match (&a.signature, &b.signature) {
+            (Some(x), Some(y)) => x.cmp(y),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => a.selector.cmp(&b.selector),
+        });
+        result
+    }
+
+    // the declared functions a transaction is expected to call. view and pure functions are
+    // read with eth_call and only kept when a transaction called them anyway.
+    // first block of the log scan. the oldest fetched transaction when the window has no lower
+    // bound or a full page cut off older transactions, otherwise the lower bound but not before
+    // the deployment, or the oldest transaction when the explorer does not know the deployment.
+    // at most MAX_LOG_SCAN_CHUNKS chunks are scanned.
+    pub fn log_scan_start(
+        window_from: u64,
+        to_block: u64,
+        has_lower_bound: bool,
+        page_full: bool,
+        oldest: Option<u64>,
+        deployed: Option<u64>,
+    ) -> u64 {
+        let from_block = if !has_lower_bound {
+            oldest.unwrap_or(to_block)
+        } else if page_full {
+            window_from.max(oldest.unwrap_or(window_from))
+        } else {
+            window_from.max(deployed.or(oldest).unwrap_or(window_from))
+        };
+        from_block.max(to_block.saturating_sub(MAX_LOG_SCAN_CHUNKS * LOG_SCAN_CHUNK - 1))
+    }
+
+    pub fn state_changing_functions(
+        declared: &HashMap<String, String>,
+        observations: &[Observation],
+    ) -> HashMap<String, String> {
+        let observed: HashSet<&str> = observations.iter().map(|o| o.selector.as_str()).collect();
+        declared
+            .iter()
+            .filter(|(selector, signature)| !is_read_only(signature) || observed.contains(selector.as_str()))
+            .map(|(selector, signature)| (selector.clone(), signature.clone()))
+            .collect()
+    }
+}
+
+// the signatures of the ABI maps carry the state mutability, e.g. "function balanceOf(address) view returns (uint256)"
+fn is_read_only(signature: &str) -> bool {
+    Function::parse(signature)
+        .is_ok_and(|f| matches!(f.state_mutability, StateMutability::View | StateMutability::Pure))
+}
+
+impl Sampler {
+    // compare the declared ABI functions/events of the contract (or its implementation
+    // if it is a proxy) with the calls and logs observed in the scanned window
+    #[instrument(name = "coverage", skip_all, fields(chain = %self.chain.name, address = %address))]
+    pub async fn coverage_report(&self, address: &str, window: &SampleWindow) -> Result<CoverageReport> {
+        let _job = JobGuard::start("coverage");
+        let chain = &self.chain;
+        let contract: Address = address.parse()?;
+        let (transactions, (window_from, to_block)) = self.normal_transactions(address, window).await?;
+
+        let proxy_detector = ProxyDetector::new(chain).await?;
+        let proxy = proxy_detector.detect_proxy_target(address).await?;
+        let (function_map, event_map) = resolved_function_event_map(chain, &contract, &proxy).await?;
+
+        let mut calls: Vec<Observation> = vec![];
+        for tx in &transactions {
+            if tx.input.is_empty() || tx.to != Some(contract) {
+                continue;
+            }
+            let (Some(method_id), Some(block_number), Some(hash)) =
+                (&tx.method_id, tx.block_number.as_number(), tx.hash.value())
+            else {
+                continue;
+            };
+            calls.push(Observation {
+                selector: method_id.to_string(),
+                block_number: block_number.to::<u64>(),
+                hash: format!("{:#x}", hash),
+            });
+        }
+
+        let oldest = transactions
+            .iter()
+            .filter_map(|tx| tx.block_number.as_number())
+            .map(|number| number.to::<u64>())
+            .min();
+        let page_full = transactions.len() as u64 >= TX_PAGE_SIZE;
+        let deployed = if window.has_lower_bound() && !page_full {
+            proxy_detector.deployment_block(contract).await
+        } else {
+            None
+        };
+        let from_block = CoverageReport::log_scan_start(
+            window_from,
+            to_block,
+            window.has_lower_bound(),
+            page_full,
+            oldest,
+            deployed,
+        );
+
+        let logs = self.contract_logs(contract, from_block, to_block).await?;
+        let function_map = CoverageReport::state_changing_functions(&function_map, &calls);
+
+        Ok(CoverageReport {
+            address: address.to_string(),
+            implementation: proxy.target.map(|addr| format!("{:#x}", addr)),
+            from_block,
+            to_block,
+            functions: CoverageReport::build_entries(CoverageKind::Function, &function_map, &calls),
+            events: CoverageReport::build_entries(CoverageKind::Event, &event_map, &logs),
+        })
+    }
+
+    // collect the event topics emitted by the contract in [from_block, to_block]
+    async fn contract_logs(&self, contract: Address, from_block: u64, to_block: u64) -> Result<Vec<Observation>> {
+        let provider = new_rpc_client(&self.chain).await?;
+
+        let filter = Filter::new().address(contract);
+        let logs = get_logs_in_chunks(provider.as_ref(), filter, from_block, to_block).await?;
+
+        let observations = logs
+            .iter()
+            .filter_map(|log| {
+                let (Some(topic0), Some(block_number), Some(hash)) =
+                    (log.topic0(), log.block_number, log.transaction_hash)
+                else {
+                    return None;
+                };
+                Some(Observation {
+                    selector: format!("{:#x}", topic0),
+                    block_number,
+                    hash: format!("{:#x}", hash),
+                })
+            })
+            .collect();
+
+        Ok(observations)
+    }
+}
@@ -0,0 +1,90 @@
+use crate::sampler::coverage::*;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn observation(selector: &str, block_number: u64, hash: &str) -> Observation {
+        Observation {
+            selector: selector.to_string(),
+            block_number,
+            hash: hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_build_entries() {
+        let declared = HashMap::from([
+            ("0xa9059cbb".to_string(), "transfer(address,uint256)".to_string()),
+            ("0x095ea7b3".to_string(), "approve(address,uint256)".to_string()),
+        ]);
+        let observations = vec![
+            observation("0xa9059cbb", 120, "0x03"),
+            observation("0xa9059cbb", 100, "0x01"),
+            observation("0xa9059cbb", 110, "0x02"),
+            observation("0xdeadbeef", 105, "0x04"),
+        ];
+
+        let entries = CoverageReport::build_entries(CoverageKind::Function, &declared, &observations);
+        assert_eq!(entries.len(), 3);
+
+        let approve = &entries[0];
+        assert_eq!(approve.signature.as_deref(), Some("approve(address,uint256)"));
+        assert!(!approve.observed());
+        assert_eq!(approve.first_seen_block, None);
+        assert_eq!(approve.sample_hash, None);
+
+        let transfer = &entries[1];
+        assert_eq!(transfer.observed_count, 3);
+        assert_eq!(transfer.first_seen_block, Some(100));
+        assert_eq!(transfer.last_seen_block, Some(120));
+        assert_eq!(transfer.sample_hash.as_deref(), Some("0x03"));
+
+        let undeclared = &entries[2];
+        assert_eq!(undeclared.selector, "0xdeadbeef");
+        assert_eq!(undeclared.signature, None);
+        assert_eq!(undeclared.observed_count, 1);
+    }
+
+    #[test]
+    fn test_log_scan_start() {
+        // no lower bound, from the oldest fetched transaction
+        assert_eq!(CoverageReport::log_scan_start(0, 20_000_000, false, false, Some(19_990_000), None), 19_990_000);
+        assert_eq!(CoverageReport::log_scan_start(0, 20_000_000, false, false, None, None), 20_000_000);
+        // a full page cut off the older transactions
+        assert_eq!(CoverageReport::log_scan_start(0, 20_000_000, true, true, Some(19_995_000), Some(5)), 19_995_000);
+        // every transaction fetched, nothing is logged before the deployment
+        assert_eq!(CoverageReport::log_scan_start(0, 20_000_000, true, false, Some(19_995_000), Some(19_990_000)), 19_990_000);
+        assert_eq!(CoverageReport::log_scan_start(0, 20_000_000, true, false, Some(19_995_000), None), 19_995_000);
+        assert_eq!(CoverageReport::log_scan_start(19_999_000, 20_000_000, true, false, None, Some(5)), 19_999_000);
+        // never more than MAX_LOG_SCAN_CHUNKS chunks
+        assert_eq!(CoverageReport::log_scan_start(0, 20_000_000, true, false, None, None), 19_000_001);
+        assert_eq!(CoverageReport::log_scan_start(0, 20_000_000, true, false, Some(1_000), Some(5)), 19_000_001);
+    }
+
+    #[test]
+    fn test_state_changing_functions() {
+        let declared = HashMap::from([
+            (
+                "0xa9059cbb".to_string(),
+                "function transfer(address to, uint256 value) returns (bool)".to_string(),
+            ),
+            (
+                "0x70a08231".to_string(),
+                "function balanceOf(address owner) view returns (uint256)".to_string(),
+            ),
+            ("0x313ce567".to_string(), "function decimals() pure returns (uint8)".to_string()),
+            ("0x06fdde03".to_string(), "function name() view returns (string)".to_string()),
+            ("0xd0e30db0".to_string(), "function deposit() payable".to_string()),
+        ]);
+        // someone sent a transaction to the view function
+        let observations = vec![observation("0x06fdde03", 100, "0x01")];
+
+        let functions = CoverageReport::state_changing_functions(&declared, &observations);
+        let mut selectors: Vec<&str> = functions.keys().map(String::as_str).collect();
+        selectors.sort();
+        assert_eq!(selectors, vec!["0x06fdde03", "0xa9059cbb", "0xd0e30db0"]);
+    }
+}
@@ -1,4 +1,13 @@
+pub mod coverage;
 pub mod sampler;
+pub mod stats;
+pub mod window;
 
 #[cfg(test)]
-mod sampler_test;
\ No newline at end of file
+mod coverage_test;
+#[cfg(test)]
+mod sampler_test;
+#[cfg(test)]
+mod stats_test;
+#[cfg(test)]
+mod window_test;
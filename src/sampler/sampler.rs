@@ -1,27 +1,67 @@
 use crate::pkg::config::{client::*, config::ChainConfig};
 use crate::model::evm::Transaction;
+use crate::pkg::proxy::proxy::{implementation_at, ProxyDetector};
+use crate::pkg::telemetry::metrics::JobGuard;
+use crate::sampler::stats::{CallRecord, MethodStats};
+use crate::sampler::window::SampleWindow;
 use alloy::hex::ToHexExt;
+use alloy::primitives::Address;
 use eyre::Result;
 use foundry_block_explorers::account::{NormalTransaction, Sort, TxListParams};
 use std::collections::HashMap;
+use tracing::instrument;
 
-pub struct Sampler {}
+// max number of transactions fetched from the block explorer per request
+pub(crate) const TX_PAGE_SIZE: u64 = 1000;
+
+pub struct Sampler {
+    pub(crate) chain: ChainConfig,
+}
+
+// a representative transaction of a method together with the method statistics
+#[derive(Debug)]
+pub struct Sample {
+    pub transaction: Transaction,
+    pub stats: MethodStats,
+}
 
 impl Sampler {
-    pub async fn transaction_samples(
-        chain: &ChainConfig,
-        address: &str,
-    ) -> Result<Vec<Transaction>> {
-        let provider = new_rpc_client(chain).await?;
-        let block_number = provider.get_block_number().await?;
+    pub fn new(chain: ChainConfig) -> Self {
+        Sampler { chain }
+    }
+
+    #[instrument(name = "sample", skip_all, fields(chain = %self.chain.name, address = %address))]
+    pub async fn transaction_samples(&self, address: &str, window: &SampleWindow) -> Result<Vec<Sample>> {
+        let _job = JobGuard::start("sample");
+        let chain = &self.chain;
+        let contract: Address = address.parse()?;
+        let (transactions, (from_block, to_block)) = self.normal_transactions(address, window).await?;
 
-        let scan_client = new_scan_client(chain)?;
-        let transactions = scan_client
-            .get_transactions(
-                &address.parse()?,
-                Some(TxListParams::new(0, block_number, 1, 1000, Sort::Desc)),
-            )
+        // sample each implementation version of an upgradeable proxy separately,
+        // only the blocks covered by the fetched transactions need the history
+        let oldest_block = transactions
+            .iter()
+            .filter_map(|tx| tx.block_number.as_number())
+            .map(|number| number.to::<u64>())
+            .min()
+            .unwrap_or(to_block)
+            .max(from_block);
+        let proxy_detector = ProxyDetector::new(chain).await?;
+        let history = proxy_detector
+            .implementation_history(contract, oldest_block, to_block)
             .await?;
+        let implementation_of = |tx: &NormalTransaction| -> Option<Address> {
+            if tx.to != Some(contract) {
+                return None;
+            }
+            implementation_at(&history, tx.block_number.as_number()?.to::<u64>())
+        };
+
+        let calls: Vec<CallRecord> = transactions
+            .iter()
+            .filter_map(|tx| Some(CallRecord::from_normal_transaction(tx)?.with_implementation(implementation_of(tx))))
+            .collect();
+        let stats = MethodStats::by_method(&calls);
 
         let mut filtered_transactions= HashMap::<String, NormalTransaction>::new();
         for tx in transactions {
@@ -32,10 +72,13 @@ impl Sampler {
 
             if let Some(method_id) = &tx.method_id {
                 // important!!! iterate the reference of tx.method_id to avoid ownership transfer
-                let method_id_str = method_id.to_string();
+                let method_id_str = match implementation_of(&tx) {
+                    Some(implementation) => format!("{:#x}{}", implementation, method_id),
+                    None => method_id.to_string(),
+                };
                 if !filtered_transactions.contains_key(&method_id_str) {
                     // if not exist, insert
-                   filtered_transactions 
+                   filtered_transactions
                         .entry(method_id_str.clone())
                         .or_insert_with(|| tx.clone());
                 }
@@ -45,12 +88,45 @@ impl Sampler {
         let mut sorted_transactions: Vec<&NormalTransaction> = filtered_transactions.values().collect();
         sorted_transactions.sort_by_key(|obj| obj.block_number.as_number());
 
-        let mut result: Vec<Transaction> = vec![];
+        let mut result: Vec<Sample> = vec![];
         for t in sorted_transactions {
-            let converted = Transaction::new(chain, t.hash.value().expect("tx hash not found").encode_hex().as_str()).await?;
-            result.push(converted);
+            let hash = t.hash.value().expect("tx hash not found").encode_hex();
+            let implementation = implementation_of(t);
+            let converted = match implementation {
+                Some(implementation) => Transaction::with_implementation(chain, &hash, implementation).await?,
+                None => Transaction::new(chain, &hash).await?,
+            };
+            let method_id = t.method_id.as_ref().map(|id| id.to_string()).unwrap_or_default();
+            let method_stats = stats.get(&(implementation, method_id)).cloned().unwrap_or_default();
+            result.push(Sample {
+                transaction: converted,
+                stats: method_stats,
+            });
         }
 
         Ok(result)
     }
+
+    // fetch the latest normal transactions of the address within the window from the block explorer,
+    // returns the transactions (newest first) and the resolved block range of the window
+    pub(crate) async fn normal_transactions(
+        &self,
+        address: &str,
+        window: &SampleWindow,
+    ) -> Result<(Vec<NormalTransaction>, (u64, u64))> {
+        let provider = new_rpc_client(&self.chain).await?;
+        let block_number = provider.get_block_number().await?;
+        let (from_block, to_block) = window.resolve(provider.as_ref(), block_number).await?;
+
+        let scan_client = new_scan_client(&self.chain)?;
+        let params = TxListParams::new(from_block, to_block, 1, TX_PAGE_SIZE, Sort::Desc);
+        let transactions = traced_explorer_call(
+            &self.chain,
+            "txlist",
+            scan_client.get_transactions(&address.parse()?, Some(params)),
+        )
+        .await?;
+
+        Ok((transactions, (from_block, to_block)))
+    }
 }
@@ -1,21 +1,30 @@
+use crate::pkg::mock::anvil::AnvilHarness;
+use crate::pkg::mock::contracts::{pingCall, pongCall, PING_ABI};
+use crate::pkg::mock::server::MockServer;
 use crate::sampler::sampler;
+use crate::sampler::window::SampleWindow;
 use crate::CONFIG;
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloy::primitives::{Address, Bytes};
+    use alloy::sol_types::SolCall;
 
     #[tokio::test]
     async fn test_transaction_samples() {
         let address_str = "0xC36442b4a4522E871399CD717aBDD847Ab11FE88";
-        let chain = CONFIG.chains.get(0).expect("no chain configuration found");
+        let chain = CONFIG.chains.first().expect("no chain configuration found");
 
-        let result = sampler::Sampler::transaction_samples(chain, &address_str).await;
+        let result =
+            sampler::Sampler::new(chain.clone()).transaction_samples(address_str, &SampleWindow::default()).await;
         match result {
-            Ok(transactions) => {
-                assert!(!transactions.is_empty(), "transactions should not be empty");
-                for tx in transactions {
-                    println!("{} {} {:?}", tx.hash, tx.method_id, tx.method_signature,);
+            Ok(samples) => {
+                assert!(!samples.is_empty(), "samples should not be empty");
+                for sample in samples {
+                    assert!(sample.stats.occurrences > 0);
+                    let tx = sample.transaction;
+                    println!("{} {} {:?} {:?}", tx.hash, tx.method_id, tx.method_signature, sample.stats);
                     if let Some(receipt) = tx.receipt {
                         for log in receipt.logs {
                             println!("{} {} {:?}", log.address, log.event_id, log.event_signature);
@@ -26,4 +35,81 @@ mod tests {
             Err(e) => panic!("{}", e),
         }
     }
+
+    #[tokio::test]
+    async fn test_transaction_samples_offline() {
+        // tests/fixtures/erc20_token.json: two transfers, one approve and a native transfer
+        let server = MockServer::start(1, "erc20_token").await.expect("mock server not started");
+        let address_str = "0xe200000000000000000000000000000000000001";
+
+        let sampler = sampler::Sampler::new(server.chain());
+        let samples = match sampler.transaction_samples(address_str, &SampleWindow::default()).await {
+            Ok(samples) => samples,
+            Err(e) => panic!("{}", e),
+        };
+
+        // one sample per method, the newest call of each, ordered by block
+        let methods: Vec<(&str, u64)> = samples
+            .iter()
+            .map(|sample| (sample.transaction.method_id.as_str(), sample.transaction.block_number))
+            .collect();
+        assert_eq!(methods, vec![("0x095ea7b3", 95), ("0xa9059cbb", 98)]);
+
+        let transfer = &samples[1];
+        assert_eq!(transfer.stats.occurrences, 2);
+        assert_eq!(transfer.stats.distinct_senders, 2);
+        assert!(transfer.transaction.method_signature.is_some());
+        assert_eq!(samples[0].stats.occurrences, 1);
+    }
+
+    #[tokio::test]
+    #[ignore = "needs the anvil binary, run with --ignored"]
+    async fn test_transaction_samples_anvil() {
+        let harness = AnvilHarness::start().await.expect("anvil not started");
+        let (alice, bob) = (harness.accounts()[0], harness.accounts()[1]);
+
+        let plain = Address::repeat_byte(0xd2);
+        let implementation = Address::repeat_byte(0xd3);
+        let proxy = Address::repeat_byte(0xd4);
+        harness.deploy_plain(plain).await.unwrap();
+        harness.deploy_plain(implementation).await.unwrap();
+        harness.deploy_eip_1967_proxy(proxy, implementation).await.unwrap();
+        harness.register_abi(implementation, PING_ABI);
+
+        let ping = Bytes::from(pingCall {}.abi_encode());
+        let pong = Bytes::from(pongCall {}.abi_encode());
+        harness.send(alice, plain, ping.clone()).await.unwrap();
+        harness.send(bob, plain, ping.clone()).await.unwrap();
+        harness.send(alice, plain, pong).await.unwrap();
+        harness.send(alice, proxy, ping).await.unwrap();
+
+        let sampler = sampler::Sampler::new(harness.chain());
+        let samples = sampler
+            .transaction_samples(&format!("{:#x}", plain), &SampleWindow::default())
+            .await
+            .unwrap();
+        assert_eq!(samples.len(), 2);
+        for sample in &samples {
+            let tx = &sample.transaction;
+            assert!(tx.method_signature.is_some(), "{} not decoded", tx.method_id);
+            let receipt = tx.receipt.as_ref().expect("receipt not found");
+            assert!(receipt.logs[0].event_signature.as_ref().unwrap().contains("Ping"));
+        }
+        let ping_sample = samples
+            .iter()
+            .find(|sample| sample.transaction.method_id == format!("0x{}", alloy::hex::encode(pingCall::SELECTOR)))
+            .expect("ping not sampled");
+        assert_eq!(ping_sample.stats.occurrences, 2);
+        assert_eq!(ping_sample.stats.distinct_senders, 2);
+
+        // calls to the proxy are decoded with the implementation ABI
+        let samples = sampler
+            .transaction_samples(&format!("{:#x}", proxy), &SampleWindow::default())
+            .await
+            .unwrap();
+        assert_eq!(samples.len(), 1);
+        let tx = &samples[0].transaction;
+        assert_eq!(tx.implementation, Some(format!("{:#x}", implementation)));
+        assert!(tx.method_signature.as_ref().unwrap().contains("ping"));
+    }
 }
@@ -0,0 +1,96 @@
+use alloy::primitives::Address;
+use foundry_block_explorers::account::NormalTransaction;
+use std::collections::{HashMap, HashSet};
+
+// one contract call in the scanned window, reduced to what the statistics need
+#[derive(Debug, Clone)]
+pub struct CallRecord {
+    pub implementation: Option<Address>, // the implementation that served the call, None for non-proxies
+    pub method_id: String,
+    pub from: Option<Address>,
+    pub failed: bool,
+    pub gas_used: u64,
+}
+
+impl CallRecord {
+    // None for native transfers and transactions without a method id
+    pub fn from_normal_transaction(tx: &NormalTransaction) -> Option<Self> {
+        if tx.input.is_empty() {
+            return None;
+        }
+        let method_id = tx.method_id.as_ref()?.to_string();
+        Some(CallRecord {
+            implementation: None,
+            method_id,
+            from: tx.from.value().cloned(),
+            failed: tx.is_error == "1",
+            gas_used: tx.gas_used.saturating_to::<u64>(),
+        })
+    }
+
+    pub fn with_implementation(mut self, implementation: Option<Address>) -> Self {
+        self.implementation = implementation;
+        self
+    }
+}
+
+// the implementation that served a call and its method id, a selector of two implementations
+// of the same proxy may map to different code
+pub type MethodKey = (Option<Address>, String);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MethodStats {
+    pub occurrences: u64,
+    pub share: f64, // occurrences / total calls in the window
+    pub distinct_senders: u64,
+    pub failure_rate: f64,
+    pub median_gas_used: u64,
+}
+
+impl MethodStats {
+    // group the calls by implementation and method id and aggregate the statistics of each method
+    pub fn by_method(calls: &[CallRecord]) -> HashMap<MethodKey, MethodStats> {
+        let mut grouped: HashMap<(Option<Address>, &str), Vec<&CallRecord>> = HashMap::new();
+        for call in calls {
+            grouped
+                .entry((call.implementation, call.method_id.as_str()))
+                .or_default()
+                .push(call);
+        }
+
+        let total = calls.len() as f64;
+        grouped
+            .into_iter()
+            .map(|((implementation, method_id), calls)| {
+                let occurrences = calls.len() as u64;
+                let failures = calls.iter().filter(|c| c.failed).count() as f64;
+                let senders: HashSet<Address> = calls.iter().filter_map(|c| c.from).collect();
+                let mut gas: Vec<u64> = calls.iter().map(|c| c.gas_used).collect();
+
+                (
+                    (implementation, method_id.to_string()),
+                    MethodStats {
+                        occurrences,
+                        share: occurrences as f64 / total,
+                        distinct_senders: senders.len() as u64,
+                        failure_rate: failures / occurrences as f64,
+                        median_gas_used: Self::median(&mut gas),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn median(values: &mut [u64]) -> u64 {
+        if values.is_empty() {
+            return 0;
+        }
+        values.sort_unstable();
+        let mid = values.len() / 2;
+        if values.len().is_multiple_of(2) {
+            (values[mid - 1] + values[mid]) / 2
+        } else {
+            values[mid]
+        }
+    }
+}
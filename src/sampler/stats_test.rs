@@ -0,0 +1,68 @@
+use crate::sampler::stats::*;
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::Address;
+
+    use super::*;
+
+    fn call(method_id: &str, from: u8, failed: bool, gas_used: u64) -> CallRecord {
+        CallRecord {
+            implementation: None,
+            method_id: method_id.to_string(),
+            from: Some(Address::with_last_byte(from)),
+            failed,
+            gas_used,
+        }
+    }
+
+    #[test]
+    fn test_method_stats() {
+        let calls = vec![
+            call("0xa9059cbb", 1, false, 50_000),
+            call("0xa9059cbb", 1, true, 30_000),
+            call("0xa9059cbb", 2, false, 52_000),
+            call("0x095ea7b3", 3, false, 46_000),
+        ];
+
+        let stats = MethodStats::by_method(&calls);
+        assert_eq!(stats.len(), 2);
+
+        let transfer = stats.get(&(None, "0xa9059cbb".to_string())).expect("transfer stats not found");
+        assert_eq!(transfer.occurrences, 3);
+        assert_eq!(transfer.share, 0.75);
+        assert_eq!(transfer.distinct_senders, 2);
+        assert!((transfer.failure_rate - 1.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(transfer.median_gas_used, 50_000);
+
+        let approve = stats.get(&(None, "0x095ea7b3".to_string())).expect("approve stats not found");
+        assert_eq!(approve.occurrences, 1);
+        assert_eq!(approve.share, 0.25);
+        assert_eq!(approve.failure_rate, 0.0);
+        assert_eq!(approve.median_gas_used, 46_000);
+    }
+
+    #[test]
+    fn test_method_stats_by_implementation() {
+        let v1 = Some(Address::with_last_byte(0xa1));
+        let v2 = Some(Address::with_last_byte(0xa2));
+        let calls = vec![
+            call("0xa9059cbb", 1, false, 50_000).with_implementation(v1),
+            call("0xa9059cbb", 2, true, 30_000).with_implementation(v2),
+            call("0xa9059cbb", 3, true, 32_000).with_implementation(v2),
+        ];
+
+        let stats = MethodStats::by_method(&calls);
+        assert_eq!(stats.len(), 2);
+
+        let before = stats.get(&(v1, "0xa9059cbb".to_string())).expect("v1 stats not found");
+        assert_eq!(before.occurrences, 1);
+        assert_eq!(before.failure_rate, 0.0);
+
+        let after = stats.get(&(v2, "0xa9059cbb".to_string())).expect("v2 stats not found");
+        assert_eq!(after.occurrences, 2);
+        assert!((after.share - 2.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(after.failure_rate, 1.0);
+        assert_eq!(after.median_gas_used, 31_000);
+    }
+}
@@ -0,0 +1,81 @@
+use alloy::providers::Provider;
+use eyre::{eyre, OptionExt, Result};
+use std::future::Future;
+
+// block and/or time bounds of a sampling window, all bounds are inclusive.
+// when both a block bound and a time bound are given, the stricter one wins.
+#[derive(Debug, Clone, Default)]
+pub struct SampleWindow {
+    pub from_block: Option<u64>,
+    pub to_block: Option<u64>,
+    pub from_time: Option<u64>, // unix timestamp in seconds
+    pub to_time: Option<u64>,   // unix timestamp in seconds
+}
+
+impl SampleWindow {
+    pub fn has_lower_bound(&self) -> bool {
+        self.from_block.is_some() || self.from_time.is_some()
+    }
+
+    // resolve the window into a block range [from, to] against the latest block
+    pub async fn resolve(&self, provider: &dyn Provider, latest_block: u64) -> Result<(u64, u64)> {
+        let timestamp_of = |number: u64| async move {
+            let block = provider
+                .get_block_by_number(alloy::eips::BlockNumberOrTag::Number(number), false)
+                .await?
+                .ok_or_eyre(format!("block not found: {}", number))?;
+            Ok::<u64, eyre::Report>(block.header.timestamp)
+        };
+
+        let mut from = self.from_block.unwrap_or(0);
+        let mut to = self.to_block.unwrap_or(latest_block).min(latest_block);
+
+        if let Some(from_time) = self.from_time {
+            let block = first_block_at_or_after(from_time, 0, latest_block, timestamp_of)
+                .await?
+                .ok_or_eyre(format!("no block at or after timestamp {}", from_time))?;
+            from = from.max(block);
+        }
+
+        if let Some(to_time) = self.to_time {
+            // the last block at or before to_time is the one before the first block after it
+            match first_block_at_or_after(to_time.saturating_add(1), 0, latest_block, timestamp_of).await? {
+                Some(0) => return Err(eyre!("no block at or before timestamp {}", to_time)),
+                Some(block) => to = to.min(block - 1),
+                None => {}
+            }
+        }
+
+        if from > to {
+            return Err(eyre!("empty sampling window: from block {} > to block {}", from, to));
+        }
+        Ok((from, to))
+    }
+}
+
+// binary search the first block in [low, high] whose timestamp is >= target,
+// None if every block in the range is earlier than target
+pub async fn first_block_at_or_after<F, Fut>(
+    target: u64,
+    mut low: u64,
+    mut high: u64,
+    timestamp_of: F,
+) -> Result<Option<u64>>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<u64>>,
+{
+    if low > high || timestamp_of(high).await? < target {
+        return Ok(None);
+    }
+
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if timestamp_of(mid).await? >= target {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    Ok(Some(low))
+}
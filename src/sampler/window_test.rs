@@ -0,0 +1,36 @@
+use crate::sampler::window::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 12 second blocks starting at timestamp 1000
+    fn timestamps() -> Vec<u64> {
+        (0..100).map(|n| 1000 + n * 12).collect()
+    }
+
+    #[tokio::test]
+    async fn test_first_block_at_or_after() {
+        let timestamps = timestamps();
+        let timestamp_of = |n: u64| {
+            let ts = timestamps[n as usize];
+            async move { Ok(ts) }
+        };
+
+        // exact hit
+        let block = first_block_at_or_after(1120, 0, 99, timestamp_of).await.unwrap();
+        assert_eq!(block, Some(10));
+
+        // between two blocks
+        let block = first_block_at_or_after(1121, 0, 99, timestamp_of).await.unwrap();
+        assert_eq!(block, Some(11));
+
+        // before the first block
+        let block = first_block_at_or_after(0, 0, 99, timestamp_of).await.unwrap();
+        assert_eq!(block, Some(0));
+
+        // after the last block
+        let block = first_block_at_or_after(1000 + 99 * 12 + 1, 0, 99, timestamp_of).await.unwrap();
+        assert_eq!(block, None);
+    }
+}